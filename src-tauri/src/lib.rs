@@ -1,5 +1,5 @@
 // use data_store::db::DbConnection;
-// use tauri::Manager;
+use tauri::Manager;
 
 mod service;
 mod module;
@@ -12,20 +12,77 @@ mod shared;
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
-        .setup(|_| {
-            shared::database::Db::setup();
-            // app.manage(db_connection);
+        .setup(|app| {
+            let path = shared::database::Db::resolve_path(app.handle())?;
+            let db = shared::database::Db::setup(&path)?;
+            module::feed::scheduler::start(app.handle().clone(), db.clone());
+            app.manage(db);
             Ok(())
         })
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_http::init())
         .invoke_handler(tauri::generate_handler![
             module::feed::api::fetch_feed,
+            module::feed::api::discover_feeds,
             module::feed::api::add_feed,
             module::feed::api::get_all_feeds,
+            module::feed::api::refresh_feed,
+            module::feed::api::refresh_all_feeds,
+            module::feed::api::set_feed_refresh_schedule,
+            module::feed::api::get_default_refresh_interval,
+            module::feed::api::set_default_refresh_interval,
+            module::feed::api::get_feed_url_history,
+            module::feed::api::update_feed,
+            module::feed::api::set_feed_categories,
+            module::feed::api::add_feed_to_category,
+            module::feed::api::remove_feed_from_category,
+            module::feed::api::move_feed,
+            module::feed::api::delete_feed,
             module::feed::category::api::add_category,
             module::feed::category::api::get_all_categories,
-            module::feed::article::api::get_articles_by_feed
+            module::feed::category::api::get_category_tree,
+            module::feed::category::api::rename_category,
+            module::feed::category::api::move_category,
+            module::feed::category::api::delete_category,
+            module::feed::opml::api::import_opml,
+            module::feed::opml::api::export_opml,
+            module::feed::tag::api::add_tag,
+            module::feed::tag::api::get_all_tags,
+            module::feed::tag::api::rename_tag,
+            module::feed::tag::api::merge_tags,
+            module::feed::tag::api::delete_tag,
+            module::feed::tag::api::tag_articles,
+            module::feed::tag::api::untag_articles,
+            module::feed::tag::api::tag_feed,
+            module::feed::tag::api::untag_feed,
+            module::feed::rule::api::add_rule,
+            module::feed::rule::api::get_all_rules,
+            module::feed::rule::api::update_rule,
+            module::feed::rule::api::delete_rule,
+            module::feed::rule::api::dry_run_rule,
+            module::feed::article::api::get_articles_by_feed,
+            module::feed::article::api::get_all_articles,
+            module::feed::article::api::get_articles_by_category,
+            module::feed::article::api::get_starred_articles,
+            module::feed::article::api::get_read_later_articles,
+            module::feed::article::api::get_hidden_articles,
+            module::feed::article::api::get_today_articles,
+            module::feed::article::api::get_article,
+            module::feed::article::api::get_unread_counts,
+            module::feed::article::api::search_articles,
+            module::feed::article::api::get_articles_by_saved_search,
+            module::feed::saved_search::api::add_saved_search,
+            module::feed::saved_search::api::get_all_saved_searches,
+            module::feed::saved_search::api::update_saved_search,
+            module::feed::saved_search::api::delete_saved_search,
+            module::feed::article::api::mark_article_read,
+            module::feed::article::api::mark_articles_read,
+            module::feed::article::api::mark_feed_read,
+            module::feed::article::api::mark_category_read,
+            module::feed::article::api::star_article,
+            module::feed::article::api::star_articles,
+            module::feed::article::api::set_article_read_later,
+            module::feed::article::api::set_article_hidden
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
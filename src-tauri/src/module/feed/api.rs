@@ -1,12 +1,15 @@
 // use crate::error;
+use crate::shared::database::Db;
 use crate::shared::errors::*;
-use crate::shared::types::Url;
+use crate::shared::types::{FeedCandidate, Url};
 
 use super::controller::FeedController;
-use super::entities::Feed;
+use super::entities::{Feed, FeedUrlChange, RefreshResult};
+use super::rule::notification;
 
 use serde_json::json;
 use serde_json::Value;
+use tauri::{AppHandle, State};
 
 #[tauri::command]
 pub async fn fetch_feed(url: Url) -> Result<Value> {
@@ -15,18 +18,138 @@ pub async fn fetch_feed(url: Url) -> Result<Value> {
 }
 
 #[tauri::command]
-pub async fn add_feed(url: Url, title: String, category_id: Option<i32>) -> Result<()> {
-    let feed_controller = FeedController::new();
-    feed_controller.add(url, title, category_id).await?;
+pub async fn discover_feeds(url: Url) -> Result<Vec<FeedCandidate>> {
+    let candidates = FeedController::discover(url).await?;
+    Ok(candidates)
+}
+
+#[tauri::command]
+pub async fn add_feed(
+    app: AppHandle,
+    db: State<'_, Db>,
+    url: Url,
+    title: String,
+    category_id: Option<i32>,
+) -> Result<()> {
+    let feed_controller = FeedController::new(db.inner().clone());
+    let notifications = feed_controller.add(url, title, category_id).await?;
+    notification::send(&app, &notifications);
     Ok(())
 }
 
 #[tauri::command]
-pub async fn get_all_feeds() -> Result<Vec<Feed>> {
-    let feeds = FeedController::new().get_all()?;
+pub async fn refresh_feed(app: AppHandle, db: State<'_, Db>, feed_id: i32) -> Result<RefreshResult> {
+    let result = FeedController::new(db.inner().clone()).refresh(feed_id).await?;
+    notification::send(&app, &result.notifications);
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn refresh_all_feeds(app: AppHandle, db: State<'_, Db>) -> Result<Vec<RefreshResult>> {
+    let results = FeedController::new(db.inner().clone()).refresh_all().await?;
+    for result in &results {
+        notification::send(&app, &result.notifications);
+    }
+    Ok(results)
+}
+
+#[tauri::command]
+pub async fn set_feed_refresh_schedule(
+    db: State<'_, Db>,
+    feed_id: i32,
+    refresh_interval: Option<i64>,
+    refresh_cron: Option<String>,
+) -> Result<()> {
+    FeedController::new(db.inner().clone())
+        .set_schedule(feed_id, refresh_interval, refresh_cron)
+        .await?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_default_refresh_interval(db: State<'_, Db>) -> Result<i64> {
+    let minutes = FeedController::new(db.inner().clone()).get_default_refresh_interval().await?;
+    Ok(minutes)
+}
+
+#[tauri::command]
+pub async fn set_default_refresh_interval(db: State<'_, Db>, minutes: i64) -> Result<()> {
+    FeedController::new(db.inner().clone()).set_default_refresh_interval(minutes).await?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_feed_url_history(db: State<'_, Db>, feed_id: i32) -> Result<Vec<FeedUrlChange>> {
+    let changes = FeedController::new(db.inner().clone()).get_url_history(feed_id).await?;
+    Ok(changes)
+}
+
+#[tauri::command]
+pub async fn get_all_feeds(db: State<'_, Db>) -> Result<Vec<Feed>> {
+    let feeds = FeedController::new(db.inner().clone()).get_all().await?;
     Ok(feeds)
 }
 
+///Fields left to None are kept as they are
+#[tauri::command]
+pub async fn update_feed(
+    db: State<'_, Db>,
+    feed_id: i32,
+    title: Option<String>,
+    xml_url: Option<Url>,
+) -> Result<()> {
+    FeedController::new(db.inner().clone()).update(feed_id, title, xml_url).await?;
+    Ok(())
+}
+
+///Replace every category of the feed, an empty list leaves it uncategorized
+#[tauri::command]
+pub async fn set_feed_categories(
+    db: State<'_, Db>,
+    feed_id: i32,
+    category_ids: Vec<i32>,
+) -> Result<()> {
+    FeedController::new(db.inner().clone()).set_categories(feed_id, category_ids).await?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn add_feed_to_category(db: State<'_, Db>, feed_id: i32, category_id: i32) -> Result<()> {
+    FeedController::new(db.inner().clone()).add_to_category(feed_id, category_id).await?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn remove_feed_from_category(
+    db: State<'_, Db>,
+    feed_id: i32,
+    category_id: i32,
+) -> Result<()> {
+    FeedController::new(db.inner().clone())
+        .remove_from_category(feed_id, category_id)
+        .await?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn move_feed(
+    db: State<'_, Db>,
+    feed_id: i32,
+    from_category_id: Option<i32>,
+    to_category_id: Option<i32>,
+) -> Result<()> {
+    FeedController::new(db.inner().clone())
+        .move_to_category(feed_id, from_category_id, to_category_id)
+        .await?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn delete_feed(db: State<'_, Db>, feed_id: i32) -> Result<usize> {
+    let deleted_articles = FeedController::new(db.inner().clone()).delete(feed_id).await?;
+    Ok(deleted_articles)
+}
+
 // #[tauri::command]
 // pub async fn get_articles_for_feed(feed_id: i32) -> Result<Vec<FeedArticle>> {
 //     let articles = FeedController::new().get_articles(feed_id)?;
@@ -1,10 +1,199 @@
 // use crate::error;
+use crate::shared::database::Db;
 use crate::shared::errors::*;
 
-use super::{controller::ArticleController, entities::Article};
+use super::{
+    controller::ArticleController,
+    entities::{
+        Article, ArticleFilter, ArticlePage, ArticlePaging, ArticleSearchFilter, ArticleSearchResult,
+        UnreadCounts,
+    },
+};
+
+use tauri::State;
+
+#[tauri::command]
+pub async fn get_articles_by_feed(
+    db: State<'_, Db>,
+    feed_id: i32,
+    filter: Option<ArticleFilter>,
+    paging: Option<ArticlePaging>,
+) -> Result<ArticlePage> {
+    let page = ArticleController::new(db.inner().clone())
+        .get_articles_by_feed(feed_id, filter.unwrap_or_default(), paging.unwrap_or_default())
+        .await?;
+    Ok(page)
+}
+
+#[tauri::command]
+pub async fn get_all_articles(
+    db: State<'_, Db>,
+    filter: Option<ArticleFilter>,
+    paging: Option<ArticlePaging>,
+) -> Result<ArticlePage> {
+    let page = ArticleController::new(db.inner().clone())
+        .get_all_articles(filter.unwrap_or_default(), paging.unwrap_or_default())
+        .await?;
+    Ok(page)
+}
+
+///Articles of a smart folder, paged and filtered like a feed
+#[tauri::command]
+pub async fn get_articles_by_saved_search(
+    db: State<'_, Db>,
+    saved_search_id: i32,
+    filter: Option<ArticleFilter>,
+    paging: Option<ArticlePaging>,
+) -> Result<ArticlePage> {
+    let page = ArticleController::new(db.inner().clone())
+        .get_articles_by_saved_search(saved_search_id, filter.unwrap_or_default(), paging.unwrap_or_default())
+        .await?;
+    Ok(page)
+}
+
+///Articles of the feeds in the category and its sub categories
+#[tauri::command]
+pub async fn get_articles_by_category(
+    db: State<'_, Db>,
+    category_id: i32,
+    filter: Option<ArticleFilter>,
+    paging: Option<ArticlePaging>,
+) -> Result<ArticlePage> {
+    let page = ArticleController::new(db.inner().clone())
+        .get_articles_by_category(category_id, filter.unwrap_or_default(), paging.unwrap_or_default())
+        .await?;
+    Ok(page)
+}
+
+#[tauri::command]
+pub async fn get_starred_articles(
+    db: State<'_, Db>,
+    filter: Option<ArticleFilter>,
+    paging: Option<ArticlePaging>,
+) -> Result<ArticlePage> {
+    let page = ArticleController::new(db.inner().clone())
+        .get_starred_articles(filter.unwrap_or_default(), paging.unwrap_or_default())
+        .await?;
+    Ok(page)
+}
+
+#[tauri::command]
+pub async fn get_read_later_articles(
+    db: State<'_, Db>,
+    filter: Option<ArticleFilter>,
+    paging: Option<ArticlePaging>,
+) -> Result<ArticlePage> {
+    let page = ArticleController::new(db.inner().clone())
+        .get_read_later_articles(filter.unwrap_or_default(), paging.unwrap_or_default())
+        .await?;
+    Ok(page)
+}
+
+#[tauri::command]
+pub async fn get_hidden_articles(
+    db: State<'_, Db>,
+    filter: Option<ArticleFilter>,
+    paging: Option<ArticlePaging>,
+) -> Result<ArticlePage> {
+    let page = ArticleController::new(db.inner().clone())
+        .get_hidden_articles(filter.unwrap_or_default(), paging.unwrap_or_default())
+        .await?;
+    Ok(page)
+}
+
+#[tauri::command]
+pub async fn get_today_articles(
+    db: State<'_, Db>,
+    filter: Option<ArticleFilter>,
+    paging: Option<ArticlePaging>,
+) -> Result<ArticlePage> {
+    let page = ArticleController::new(db.inner().clone())
+        .get_today_articles(filter.unwrap_or_default(), paging.unwrap_or_default())
+        .await?;
+    Ok(page)
+}
+
+#[tauri::command]
+pub async fn get_article(db: State<'_, Db>, article_id: i32) -> Result<Article> {
+    let article = ArticleController::new(db.inner().clone()).get_article(article_id).await?;
+    Ok(article)
+}
+
+///Best matches first, e.g. `feed:"Rust Blog" title:release -is:read after:2026-01-01`
+///A query that can't be parsed fails with `QueryParse` and the position of the problem
+#[tauri::command]
+pub async fn search_articles(
+    db: State<'_, Db>,
+    query: String,
+    filter: Option<ArticleSearchFilter>,
+) -> Result<Vec<ArticleSearchResult>> {
+    let results = ArticleController::new(db.inner().clone())
+        .search(query, filter.unwrap_or_default())
+        .await?;
+    Ok(results)
+}
+
+#[tauri::command]
+pub async fn get_unread_counts(db: State<'_, Db>) -> Result<UnreadCounts> {
+    let counts = ArticleController::new(db.inner().clone()).get_unread_counts().await?;
+    Ok(counts)
+}
+
+#[tauri::command]
+pub async fn mark_article_read(db: State<'_, Db>, article_id: i32, read: bool) -> Result<usize> {
+    let updated = ArticleController::new(db.inner().clone()).mark_read(vec![article_id], read).await?;
+    Ok(updated)
+}
+
+#[tauri::command]
+pub async fn mark_articles_read(
+    db: State<'_, Db>,
+    article_ids: Vec<i32>,
+    read: bool,
+) -> Result<usize> {
+    let updated = ArticleController::new(db.inner().clone()).mark_read(article_ids, read).await?;
+    Ok(updated)
+}
+
+#[tauri::command]
+pub async fn mark_feed_read(db: State<'_, Db>, feed_id: i32, read: bool) -> Result<usize> {
+    let updated = ArticleController::new(db.inner().clone()).mark_feed_read(feed_id, read).await?;
+    Ok(updated)
+}
+
+///Marks the articles of every feed in the category and its sub categories
+#[tauri::command]
+pub async fn mark_category_read(db: State<'_, Db>, category_id: i32, read: bool) -> Result<usize> {
+    let updated = ArticleController::new(db.inner().clone())
+        .mark_category_read(category_id, read)
+        .await?;
+    Ok(updated)
+}
+
+#[tauri::command]
+pub async fn star_article(db: State<'_, Db>, article_id: i32, starred: bool) -> Result<usize> {
+    let updated = ArticleController::new(db.inner().clone()).star(vec![article_id], starred).await?;
+    Ok(updated)
+}
+
+#[tauri::command]
+pub async fn star_articles(db: State<'_, Db>, article_ids: Vec<i32>, starred: bool) -> Result<usize> {
+    let updated = ArticleController::new(db.inner().clone()).star(article_ids, starred).await?;
+    Ok(updated)
+}
+
+#[tauri::command]
+pub async fn set_article_read_later(db: State<'_, Db>, article_id: i32, read_later: bool) -> Result<usize> {
+    let updated = ArticleController::new(db.inner().clone())
+        .read_later(vec![article_id], read_later)
+        .await?;
+    Ok(updated)
+}
 
 #[tauri::command]
-pub async fn get_articles_by_feed(feed_id: i32) -> Result<Vec<Article>> {
-    let articles = ArticleController::get_articles_by_feed(feed_id)?;
-    Ok(articles)
+pub async fn set_article_hidden(db: State<'_, Db>, article_id: i32, hidden: bool) -> Result<usize> {
+    let updated = ArticleController::new(db.inner().clone())
+        .hide(vec![article_id], hidden)
+        .await?;
+    Ok(updated)
 }
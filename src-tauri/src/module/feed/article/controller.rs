@@ -1,33 +1,332 @@
-// use crate::error;
+use crate::error;
+use crate::shared::database::Db;
 use crate::shared::errors::*;
 
-use super::{entities::Article, model::ArticleModel};
-pub(super) struct ArticleController {}
+use chrono::{Local, TimeZone};
+
+use super::super::saved_search::model::SavedSearchModel;
+use super::super::tag::model::TagModel;
+use super::{
+    entities::{
+        Article, ArticleCursor, ArticleFilter, ArticleListItem, ArticlePage, ArticlePaging,
+        ArticleSearchFilter, ArticleSearchResult, ArticleView, UnreadCount, UnreadCounts,
+    },
+    model::{ArticleListRow, ArticleModel},
+    query,
+};
+
+const DEFAULT_PAGE_SIZE: u32 = 50;
+const MAX_PAGE_SIZE: u32 = 500;
+
+pub(in crate::module::feed) struct ArticleController {
+    db: Db,
+}
 
 impl ArticleController {
-    pub fn _new() -> Self {
-        ArticleController {}
+    pub fn new(db: Db) -> Self {
+        ArticleController { db }
     }
 
     // pub fn get_articles() {}
-    pub fn get_articles_by_feed(feed_id: i32) -> Result<Vec<Article>> {
-        let mut articles = Vec::new();
-        let model_articles = ArticleModel::new().open()?.get_articles_by_feed(feed_id)?;
+    pub async fn get_articles_by_feed(
+        &self,
+        feed_id: i32,
+        filter: ArticleFilter,
+        paging: ArticlePaging,
+    ) -> Result<ArticlePage> {
+        self.get_articles(ArticleView::Feed(feed_id), filter, paging).await
+    }
 
-        let _: Vec<_> = model_articles
-            .into_iter()
-            .map(|i| {
-                articles.push(
-                    Article::builder()
-                        .id(i.0)
-                        .title(i.1)
-                        .content(i.2)
-                        .feed_id(vec![feed_id])
-                        .build(),
+    pub async fn get_all_articles(
+        &self,
+        filter: ArticleFilter,
+        paging: ArticlePaging,
+    ) -> Result<ArticlePage> {
+        self.get_articles(ArticleView::All, filter, paging).await
+    }
+
+    pub async fn get_articles_by_category(
+        &self,
+        category_id: i32,
+        filter: ArticleFilter,
+        paging: ArticlePaging,
+    ) -> Result<ArticlePage> {
+        self.get_articles(ArticleView::Category(category_id), filter, paging)
+            .await
+    }
+
+    pub async fn get_starred_articles(
+        &self,
+        filter: ArticleFilter,
+        paging: ArticlePaging,
+    ) -> Result<ArticlePage> {
+        self.get_articles(ArticleView::Starred, filter, paging).await
+    }
+
+    pub async fn get_read_later_articles(
+        &self,
+        filter: ArticleFilter,
+        paging: ArticlePaging,
+    ) -> Result<ArticlePage> {
+        self.get_articles(ArticleView::ReadLater, filter, paging).await
+    }
+
+    pub async fn get_hidden_articles(
+        &self,
+        filter: ArticleFilter,
+        paging: ArticlePaging,
+    ) -> Result<ArticlePage> {
+        self.get_articles(ArticleView::Hidden, filter, paging).await
+    }
+
+    ///Articles of a smart folder, matching the saved search query
+    pub async fn get_articles_by_saved_search(
+        &self,
+        saved_search_id: i32,
+        filter: ArticleFilter,
+        paging: ArticlePaging,
+    ) -> Result<ArticlePage> {
+        let saved_search = self
+            .db
+            .call(move |connection| SavedSearchModel::new(connection).get_saved_search(saved_search_id))
+            .await?;
+        let Some(expr) = query::parse(&saved_search.query)? else {
+            return Ok(ArticlePage {
+                articles: Vec::new(),
+                next_cursor: None,
+            });
+        };
+        self.get_articles(ArticleView::Search(query::compile(&expr)), filter, paging)
+            .await
+    }
+
+    ///Articles published since local midnight
+    pub async fn get_today_articles(
+        &self,
+        filter: ArticleFilter,
+        paging: ArticlePaging,
+    ) -> Result<ArticlePage> {
+        let midnight = Local::now()
+            .date_naive()
+            .and_hms_opt(0, 0, 0)
+            .and_then(|midnight| Local.from_local_datetime(&midnight).earliest())
+            .ok_or(error!(ErrorType::Custom("no local midnight today".to_string())))?;
+        self.get_articles(ArticleView::Today(midnight.timestamp()), filter, paging)
+            .await
+    }
+
+    async fn get_articles(
+        &self,
+        view: ArticleView,
+        filter: ArticleFilter,
+        paging: ArticlePaging,
+    ) -> Result<ArticlePage> {
+        let limit = paging.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+        // One more than asked tells whether there is a next page
+        let mut model_articles = self
+            .db
+            .call(move |connection| {
+                ArticleModel::new(connection).get_articles(
+                    &view,
+                    &filter,
+                    paging.sort,
+                    paging.after.as_ref(),
+                    limit + 1,
+                )
+            })
+            .await?;
+
+        let has_more = model_articles.len() > limit as usize;
+        model_articles.truncate(limit as usize);
+        let next_cursor = match model_articles.last() {
+            Some(last) if has_more => Some(ArticleCursor {
+                published_at: last.published_at,
+                id: last.id,
+            }),
+            _ => None,
+        };
+
+        let articles = model_articles.into_iter().map(Self::list_item_from_row).collect();
+
+        Ok(ArticlePage {
+            articles,
+            next_cursor,
+        })
+    }
+
+    pub(in crate::module::feed) fn list_item_from_row(i: ArticleListRow) -> ArticleListItem {
+        ArticleListItem {
+            id: i.id,
+            feed_id: i.feed_id,
+            title: i.title,
+            link: i.link,
+            summary: i.summary,
+            author: i.author,
+            published_at: i.published_at,
+            is_read: i.is_read,
+            is_starred: i.is_starred,
+        }
+    }
+
+    ///See `query` for the query language, words without a field have to be found in the title, content or author
+    pub async fn search(
+        &self,
+        query: String,
+        filter: ArticleSearchFilter,
+    ) -> Result<Vec<ArticleSearchResult>> {
+        let Some(expr) = query::parse(&query)? else {
+            return Ok(Vec::new());
+        };
+        let compiled = query::compile(&expr);
+        let limit = filter.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+        let offset = filter.offset.unwrap_or(0);
+
+        let model_results = self
+            .db
+            .call(move |connection| {
+                ArticleModel::new(connection).search_articles(
+                    &compiled.condition,
+                    compiled.values,
+                    compiled.highlight.as_deref(),
+                    &filter,
+                    limit,
+                    offset,
                 )
             })
-            .collect();
+            .await?;
 
-        Ok(articles)
+        Ok(model_results
+            .into_iter()
+            .map(|i| ArticleSearchResult {
+                article: Self::list_item_from_row(i.article),
+                title_highlight: marked_html(&i.title_highlight),
+                snippet: marked_html(&i.snippet),
+                rank: i.rank,
+            })
+            .collect())
+    }
+
+    ///Full article, with its content
+    pub async fn get_article(&self, article_id: i32) -> Result<Article> {
+        let (i, categories, tag_ids) = self
+            .db
+            .call(move |connection| {
+                let article_model = ArticleModel::new(connection);
+                Ok((
+                    article_model.get_article(article_id)?,
+                    article_model.get_categories(article_id)?,
+                    TagModel::new(connection).get_article_tag_ids(article_id)?,
+                ))
+            })
+            .await?;
+
+        Ok(Article::builder()
+            .id(i.id)
+            .guid(i.guid)
+            .title(i.title)
+            .link(i.link)
+            .content(i.content)
+            .summary(i.summary)
+            .pub_date(i.pub_date)
+            .updated_date(i.updated_date)
+            .published_at(i.published_at)
+            .updated_at(i.updated_at)
+            .author(i.author)
+            .comments_url(i.comments_url)
+            .read(i.is_read, i.read_at)
+            .starred(i.is_starred, i.starred_at)
+            .read_later(i.is_read_later, i.read_later_at)
+            .categories(categories)
+            .tag_ids(tag_ids)
+            .feed_id(i.feed_id.into_iter().collect())
+            .build())
+    }
+
+    ///Returns how many articles changed state
+    pub async fn mark_read(&self, article_ids: Vec<i32>, is_read: bool) -> Result<usize> {
+        let now = Local::now().timestamp();
+        self.db
+            .transaction(move |transaction| {
+                ArticleModel::new(transaction).update_read(&article_ids, is_read, now)
+            })
+            .await
+    }
+
+    pub async fn mark_feed_read(&self, feed_id: i32, is_read: bool) -> Result<usize> {
+        let now = Local::now().timestamp();
+        self.db
+            .call(move |connection| {
+                ArticleModel::new(connection).update_feed_read(feed_id, is_read, now)
+            })
+            .await
+    }
+
+    pub async fn mark_category_read(&self, category_id: i32, is_read: bool) -> Result<usize> {
+        let now = Local::now().timestamp();
+        self.db
+            .call(move |connection| {
+                ArticleModel::new(connection).update_category_read(category_id, is_read, now)
+            })
+            .await
     }
+
+    pub async fn star(&self, article_ids: Vec<i32>, is_starred: bool) -> Result<usize> {
+        let now = Local::now().timestamp();
+        self.db
+            .transaction(move |transaction| {
+                ArticleModel::new(transaction).update_starred(&article_ids, is_starred, now)
+            })
+            .await
+    }
+
+    pub async fn read_later(&self, article_ids: Vec<i32>, is_read_later: bool) -> Result<usize> {
+        let now = Local::now().timestamp();
+        self.db
+            .transaction(move |transaction| {
+                ArticleModel::new(transaction).update_read_later(&article_ids, is_read_later, now)
+            })
+            .await
+    }
+
+    ///Hidden articles are only listed by `get_hidden_articles`, unhiding brings them back everywhere
+    pub async fn hide(&self, article_ids: Vec<i32>, is_hidden: bool) -> Result<usize> {
+        self.db
+            .transaction(move |transaction| {
+                ArticleModel::new(transaction).update_hidden(&article_ids, is_hidden)
+            })
+            .await
+    }
+
+    pub async fn get_unread_counts(&self) -> Result<UnreadCounts> {
+        let (total, feeds, categories) = self
+            .db
+            .call(|connection| {
+                let article_model = ArticleModel::new(connection);
+                Ok((
+                    article_model.get_unread_count()?,
+                    article_model.get_feed_unread_counts()?,
+                    article_model.get_category_unread_counts()?,
+                ))
+            })
+            .await?;
+
+        let to_counts = |counts: Vec<(i32, i64)>| {
+            counts
+                .into_iter()
+                .map(|(id, unread)| UnreadCount { id, unread })
+                .collect()
+        };
+        Ok(UnreadCounts {
+            total,
+            feeds: to_counts(feeds),
+            categories: to_counts(categories),
+        })
+    }
+}
+
+///The index holds plain text, escape it and turn the matches between \u{2} and \u{3} into `<mark>`
+fn marked_html(fragment: &str) -> String {
+    quick_xml::escape::escape(fragment)
+        .replace('\u{2}', "<mark>")
+        .replace('\u{3}', "</mark>")
 }
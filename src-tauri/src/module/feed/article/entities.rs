@@ -1,18 +1,163 @@
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
 // use crate::shared::errors::*;
+use crate::shared::date::parse_timestamp;
+
+use super::query::CompiledQuery;
 use super::super::entities;
 use entities::AtomEntry;
 use entities::Item;
+use entities::JsonItem;
+use entities::RdfItem;
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Article {
     pub(super) id: Option<i32>,
     pub(super) feed_id: Option<Vec<i32>>,
+    pub(super) guid: String,
     pub(super) title: String,
     pub(super) link: Option<String>,
+    ///Full body when the feed has one, otherwise the same as the summary
     pub(super) content: String,
+    pub(super) summary: Option<String>,
+    ///Dates as found in the feed
     pub(super) pub_date: Option<String>,
+    pub(super) updated_date: Option<String>,
+    ///Unix timestamps (UTC), published_at falls back to when the article was first seen
+    pub(super) published_at: Option<i64>,
+    pub(super) updated_at: Option<i64>,
+    pub(super) author: Option<String>,
+    pub(super) comments_url: Option<String>,
+    pub(super) is_read: bool,
+    pub(super) read_at: Option<i64>,
+    pub(super) is_starred: bool,
+    pub(super) starred_at: Option<i64>,
+    pub(super) is_read_later: bool,
+    pub(super) read_later_at: Option<i64>,
+    ///Categories the feed gave the article, not the user's categories
+    #[serde(default)]
+    pub(super) categories: Vec<String>,
+    #[serde(default)]
+    pub(super) tag_ids: Vec<i32>,
+}
+
+///Which articles a listing is about
+#[derive(Debug)]
+pub enum ArticleView {
+    Feed(i32),
+    ///Every subscribed feed, the "river of news"
+    All,
+    ///Feeds of the category and its sub categories
+    Category(i32),
+    Starred,
+    ///Put aside by the user or a rule
+    ReadLater,
+    ///Hidden by a rule or the user, the other views leave them out
+    Hidden,
+    ///Published since the timestamp, the start of the local day
+    Today(i64),
+    ///Matching a search query, for saved searches
+    Search(CompiledQuery),
+}
+
+#[derive(Debug, Serialize)]
+pub struct UnreadCount {
+    pub id: i32,
+    pub unread: i64,
+}
+
+///Feeds and categories without unread articles are left out
+#[derive(Debug, Serialize)]
+pub struct UnreadCounts {
+    pub total: i64,
+    pub feeds: Vec<UnreadCount>,
+    ///Rolled up through the sub categories
+    pub categories: Vec<UnreadCount>,
+}
+
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArticleSort {
+    #[default]
+    Newest,
+    Oldest,
+    ///Order the articles were first saved in, articles found by the same refresh keep the feed's order
+    Inserted,
+}
+
+///Last article of a page, handed back to get the articles after it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArticleCursor {
+    pub published_at: i64,
+    pub id: i32,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ArticlePaging {
+    pub sort: ArticleSort,
+    pub limit: Option<u32>,
+    pub after: Option<ArticleCursor>,
+}
+
+///What a listing shows of an article, the body is fetched on its own with `get_article`
+#[derive(Debug, Serialize)]
+pub struct ArticleListItem {
+    pub(super) id: i32,
+    pub(super) feed_id: Option<i32>,
+    pub(super) title: String,
+    pub(super) link: Option<String>,
+    pub(super) summary: Option<String>,
+    pub(super) author: Option<String>,
+    pub(super) published_at: i64,
+    pub(super) is_read: bool,
+    pub(super) is_starred: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ArticlePage {
+    pub articles: Vec<ArticleListItem>,
+    ///None on the last page
+    pub next_cursor: Option<ArticleCursor>,
+}
+
+///Restrictions on an article listing, everything is included by default
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ArticleFilter {
+    pub unread_only: bool,
+    pub starred_only: bool,
+    ///Tagged directly or through their feed
+    pub tag_id: Option<i32>,
+}
+
+///Restrictions on a search, everything is included by default
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ArticleSearchFilter {
+    pub feed_id: Option<i32>,
+    ///The category and the ones below it
+    pub category_id: Option<i32>,
+    ///Published at or after, unix timestamp
+    pub since: Option<i64>,
+    ///Published before, unix timestamp
+    pub until: Option<i64>,
+    pub is_read: Option<bool>,
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+}
+
+///An article matching a search, best matches first
+#[derive(Debug, Serialize)]
+pub struct ArticleSearchResult {
+    #[serde(flatten)]
+    pub(super) article: ArticleListItem,
+    ///Title and excerpt of the content as HTML, matches wrapped in `<mark>`
+    pub(super) title_highlight: String,
+    pub(super) snippet: String,
+    ///bm25 score, lower is better
+    pub(super) rank: f64,
 }
 
 impl Article {
@@ -30,46 +175,283 @@ impl Article {
         ArticleBuilder {
             id: None,
             feed_id: None,
+            guid: None,
             title: None,
             link: None,
             content: None,
+            summary: None,
             pub_date: None,
+            updated_date: None,
+            published_at: None,
+            updated_at: None,
+            author: None,
+            comments_url: None,
+            is_read: false,
+            read_at: None,
+            is_starred: false,
+            starred_at: None,
+            is_read_later: false,
+            read_later_at: None,
+            categories: Vec::new(),
+            tag_ids: Vec::new(),
         }
     }
 
     #[allow(private_interfaces)]//TODO: revisit later
     pub fn from_rss(item: Item) -> Self {
+        // isPermaLink defaults to true, the guid is then the article's url
+        let permalink = item
+            .guid
+            .as_ref()
+            .filter(|guid| guid.is_permalink.unwrap_or(true))
+            .and_then(|guid| guid.value.clone())
+            .filter(|guid| guid.starts_with("http://") || guid.starts_with("https://"));
+        let link = item.link.filter(|link| !link.trim().is_empty()).or(permalink);
+        let guid = match item.guid.and_then(|guid| guid.value) {
+            Some(guid) => guid,
+            None => fallback_guid(link.as_deref(), &item.title),
+        };
+        // With content:encoded the description is only a teaser
+        let (content, summary) = match item.encoded {
+            Some(encoded) => (encoded, item.description),
+            None => (item.description.unwrap_or_default(), None),
+        };
+        let pub_date = item.pub_date.or(item.date);
+        let categories = item_categories(
+            item.category
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|category| category.value),
+        );
         Self {
             id: None,
             feed_id: None,
+            guid,
             title: item.title,
-            link: None, // rss link handling
-            content: item.description,
-            pub_date: item.pub_date,
+            link,
+            content,
+            summary,
+            published_at: pub_date.as_deref().and_then(parse_timestamp),
+            updated_at: None,
+            pub_date,
+            updated_date: None,
+            author: item.author.or(item.creator),
+            comments_url: item.comments,
+            is_read: false,
+            read_at: None,
+            is_starred: false,
+            starred_at: None,
+            is_read_later: false,
+            read_later_at: None,
+            categories,
+            tag_ids: Vec::new(),
         }
     }
-    
+
     #[allow(private_interfaces)]//TODO: revisit later
     pub fn from_atom(entry: AtomEntry) -> Self {
+        // A link without rel is an alternate link
+        let link = entry.links.as_ref().and_then(|links| {
+            links
+                .iter()
+                .find(|link| matches!(link.rel.as_deref(), None | Some("alternate")))
+                .or(links.first())
+                .and_then(|link| link.href.clone())
+        });
+        let guid = match entry.id {
+            Some(id) => id,
+            None => fallback_guid(link.as_deref(), &entry.title),
+        };
+        let author = entry
+            .authors
+            .and_then(|authors| authors.into_iter().find_map(|author| author.name));
+        let content = match entry.content {
+            Some(content) => content.content,
+            None => entry.summary.clone().unwrap_or_default(),
+        };
+        let pub_date = entry.published.or(entry.updated.clone());
+        let categories = item_categories(
+            entry
+                .categories
+                .into_iter()
+                .filter_map(|category| category.label.or(category.term)),
+        );
         Self {
             id: None,
             feed_id: None,
+            guid,
             title: entry.title,
-            // link: entry.link.map(|l| l.href),
-            link: None,
-            content: entry.content.content,
-            pub_date: entry.published,
+            link,
+            content,
+            summary: entry.summary,
+            published_at: pub_date.as_deref().and_then(parse_timestamp),
+            updated_at: entry.updated.as_deref().and_then(parse_timestamp),
+            pub_date,
+            updated_date: entry.updated,
+            author,
+            comments_url: None,
+            is_read: false,
+            read_at: None,
+            is_starred: false,
+            starred_at: None,
+            is_read_later: false,
+            read_later_at: None,
+            categories,
+            tag_ids: Vec::new(),
+        }
+    }
+
+    #[allow(private_interfaces)]//TODO: revisit later
+    pub fn from_rdf(item: RdfItem, channel_creator: Option<&str>) -> Self {
+        // rdf:about is the item's URI, the closest thing RSS 1.0 has to a guid
+        let guid = match item.about.as_deref().or(item.link.as_deref()) {
+            Some(about) => about.to_string(),
+            None => fallback_guid(None, &item.title),
+        };
+        let (content, summary) = match item.encoded {
+            Some(encoded) => (encoded, item.description),
+            None => (item.description.unwrap_or_default(), None),
+        };
+        // dc:subject
+        let categories = item_categories(item.subjects);
+        Self {
+            id: None,
+            feed_id: None,
+            guid,
+            title: item.title,
+            link: item.link,
+            content,
+            summary,
+            published_at: item.date.as_deref().and_then(parse_timestamp),
+            updated_at: None,
+            pub_date: item.date,
+            updated_date: None,
+            author: item.creator.or(channel_creator.map(str::to_string)),
+            comments_url: None,
+            is_read: false,
+            read_at: None,
+            is_starred: false,
+            starred_at: None,
+            is_read_later: false,
+            read_later_at: None,
+            categories,
+            tag_ids: Vec::new(),
+        }
+    }
+
+    #[allow(private_interfaces)]//TODO: revisit later
+    pub fn from_json(item: JsonItem) -> Self {
+        let link = item.url.or(item.external_url);
+        let content = item
+            .content_html
+            .or(item.content_text)
+            .or(item.summary.clone())
+            .unwrap_or_default();
+        // Titles are optional in JSON Feed, microblog posts usually don't have one
+        let title = item
+            .title
+            .filter(|title| !title.trim().is_empty())
+            .or(item.summary.clone())
+            .unwrap_or_else(|| content.chars().take(80).collect());
+        let guid = match item.id {
+            Some(serde_json::Value::String(id)) => id,
+            Some(serde_json::Value::Number(id)) => id.to_string(),
+            _ => fallback_guid(link.as_deref(), &title),
+        };
+        let author = item
+            .authors
+            .and_then(|authors| authors.into_iter().find_map(|author| author.name))
+            .or(item.author.and_then(|author| author.name));
+        let categories = item_categories(item.tags.unwrap_or_default());
+        Self {
+            id: None,
+            feed_id: None,
+            guid,
+            title,
+            link,
+            content,
+            summary: item.summary,
+            published_at: item.date_published.as_deref().and_then(parse_timestamp),
+            updated_at: item.date_modified.as_deref().and_then(parse_timestamp),
+            pub_date: item.date_published,
+            updated_date: item.date_modified,
+            author,
+            comments_url: None,
+            is_read: false,
+            read_at: None,
+            is_starred: false,
+            starred_at: None,
+            is_read_later: false,
+            read_later_at: None,
+            categories,
+            tag_ids: Vec::new(),
+        }
+    }
+
+    ///Hash of the user visible fields, used to detect articles edited upstream
+    pub(super) fn content_hash(&self) -> String {
+        digest(&[
+            &self.title,
+            &self.content,
+            self.summary.as_deref().unwrap_or(""),
+            self.link.as_deref().unwrap_or(""),
+            self.updated_date.as_deref().unwrap_or(""),
+        ])
+    }
+}
+
+///Trimmed, without blanks and duplicates
+fn item_categories(names: impl IntoIterator<Item = String>) -> Vec<String> {
+    let mut categories: Vec<String> = Vec::new();
+    for name in names {
+        let name = name.trim();
+        if !name.is_empty() && !categories.iter().any(|category| category == name) {
+            categories.push(name.to_string());
         }
     }
+    categories
+}
+
+///Identity for items that don't carry a guid/id of their own
+fn fallback_guid(link: Option<&str>, title: &str) -> String {
+    digest(&[link.unwrap_or(""), title])
+}
+
+fn digest(parts: &[&str]) -> String {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        hasher.update(part.as_bytes());
+        hasher.update([0u8]);
+    }
+    hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
 }
 
 pub(super) struct ArticleBuilder {
     id: Option<i32>,
     feed_id: Option<Vec<i32>>,
+    guid: Option<String>,
     title: Option<String>,
     link: Option<String>,
     content: Option<String>,
+    summary: Option<String>,
     pub_date: Option<String>,
+    updated_date: Option<String>,
+    published_at: Option<i64>,
+    updated_at: Option<i64>,
+    author: Option<String>,
+    comments_url: Option<String>,
+    is_read: bool,
+    read_at: Option<i64>,
+    is_starred: bool,
+    starred_at: Option<i64>,
+    is_read_later: bool,
+    read_later_at: Option<i64>,
+    categories: Vec<String>,
+    tag_ids: Vec<i32>,
 }
 
 impl ArticleBuilder {
@@ -81,20 +463,72 @@ impl ArticleBuilder {
         self.feed_id = Some(feed_id);
         self
     }
+    pub fn guid(mut self, guid: String) -> Self {
+        self.guid = Some(guid);
+        self
+    }
     pub fn title(mut self, title: String) -> Self {
         self.title = Some(title);
         self
     }
-    pub fn _link(mut self, link: String) -> Self {
-        self.link = Some(link);
+    pub fn link(mut self, link: Option<String>) -> Self {
+        self.link = link;
         self
     }
     pub fn content(mut self, content: String) -> Self {
         self.content = Some(content);
         self
     }
-    pub fn _pub_date(mut self, pub_date: String) -> Self {
-        self.pub_date = Some(pub_date);
+    pub fn summary(mut self, summary: Option<String>) -> Self {
+        self.summary = summary;
+        self
+    }
+    pub fn pub_date(mut self, pub_date: Option<String>) -> Self {
+        self.pub_date = pub_date;
+        self
+    }
+    pub fn updated_date(mut self, updated_date: Option<String>) -> Self {
+        self.updated_date = updated_date;
+        self
+    }
+    pub fn published_at(mut self, published_at: Option<i64>) -> Self {
+        self.published_at = published_at;
+        self
+    }
+    pub fn updated_at(mut self, updated_at: Option<i64>) -> Self {
+        self.updated_at = updated_at;
+        self
+    }
+    pub fn author(mut self, author: Option<String>) -> Self {
+        self.author = author;
+        self
+    }
+    pub fn comments_url(mut self, comments_url: Option<String>) -> Self {
+        self.comments_url = comments_url;
+        self
+    }
+
+    pub fn read(mut self, is_read: bool, read_at: Option<i64>) -> Self {
+        self.is_read = is_read;
+        self.read_at = read_at;
+        self
+    }
+    pub fn starred(mut self, is_starred: bool, starred_at: Option<i64>) -> Self {
+        self.is_starred = is_starred;
+        self.starred_at = starred_at;
+        self
+    }
+    pub fn read_later(mut self, is_read_later: bool, read_later_at: Option<i64>) -> Self {
+        self.is_read_later = is_read_later;
+        self.read_later_at = read_later_at;
+        self
+    }
+    pub fn categories(mut self, categories: Vec<String>) -> Self {
+        self.categories = categories;
+        self
+    }
+    pub fn tag_ids(mut self, tag_ids: Vec<i32>) -> Self {
+        self.tag_ids = tag_ids;
         self
     }
 
@@ -102,10 +536,25 @@ impl ArticleBuilder {
         Article {
             id: self.id,
             feed_id: self.feed_id,
+            guid: self.guid.unwrap_or("".to_string()),
             title: self.title.unwrap_or("".to_string()),
             link: self.link,
             content: self.content.unwrap_or("".to_string()),
+            summary: self.summary,
             pub_date: self.pub_date,
+            updated_date: self.updated_date,
+            published_at: self.published_at,
+            updated_at: self.updated_at,
+            author: self.author,
+            comments_url: self.comments_url,
+            is_read: self.is_read,
+            read_at: self.read_at,
+            is_starred: self.is_starred,
+            starred_at: self.starred_at,
+            is_read_later: self.is_read_later,
+            read_later_at: self.read_later_at,
+            categories: self.categories,
+            tag_ids: self.tag_ids,
         }
     }
 }
@@ -2,6 +2,7 @@ pub(super) mod controller;
 pub(super) mod model;
 // pub(super) mod business;
 pub(super) mod entities;
+pub(in crate::module::feed) mod query;
 pub mod api;
 
 // use pub(super) entities;
\ No newline at end of file
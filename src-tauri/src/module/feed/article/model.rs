@@ -1,33 +1,94 @@
 use crate::error;
-use crate::shared::database::{Db, IDb};
 use crate::shared::errors::*;
+use crate::shared::html::plain_text;
+use rusqlite::{Connection, OptionalExtension};
 
-use super::entities::Article;
+use super::super::category::model::CATEGORY_SUBTREE;
+use super::query::CompiledQuery;
+use super::entities::{
+    Article, ArticleCursor, ArticleFilter, ArticleSearchFilter, ArticleSort, ArticleView,
+};
 
-pub struct ArticleModel {
-    db: Db,
+pub struct ArticleModel<'a> {
+    connection: &'a Connection,
 }
 
-impl ArticleModel {
-    pub fn new() -> Self {
-        ArticleModel { db: Db::new() }
-    }
+pub struct ArticleRow {
+    pub id: i32,
+    pub feed_id: Option<i32>,
+    pub guid: String,
+    pub title: String,
+    pub link: Option<String>,
+    pub content: String,
+    pub summary: Option<String>,
+    pub pub_date: Option<String>,
+    pub updated_date: Option<String>,
+    pub published_at: Option<i64>,
+    pub updated_at: Option<i64>,
+    pub author: Option<String>,
+    pub comments_url: Option<String>,
+    pub is_read: bool,
+    pub read_at: Option<i64>,
+    pub is_starred: bool,
+    pub starred_at: Option<i64>,
+    pub is_read_later: bool,
+    pub read_later_at: Option<i64>,
+}
 
-    pub fn open(mut self) -> Result<Self> {
-        self.db.open()?;
-        Ok(self)
-    }
+pub struct ArticleListRow {
+    pub id: i32,
+    pub feed_id: Option<i32>,
+    pub title: String,
+    pub link: Option<String>,
+    pub summary: Option<String>,
+    pub author: Option<String>,
+    pub published_at: i64,
+    pub is_read: bool,
+    pub is_starred: bool,
+}
+
+///What rules look at, with the listing fields to show the matches
+pub struct RuleCandidateRow {
+    pub article: ArticleListRow,
+    pub content: String,
+    pub categories: Vec<String>,
+}
+
+///Matches in the highlight and snippet are between \u{2} and \u{3}
+pub struct ArticleSearchRow {
+    pub article: ArticleListRow,
+    pub title_highlight: String,
+    pub snippet: String,
+    pub rank: f64,
+}
+
+///Column weights of the search ranking: title, content, author
+const SEARCH_RANK: &str = "bm25(article_search, 10.0, 1.0, 5.0)";
+
+///Articles with the tag, or whose feed has it. Takes the tag id twice
+const TAGGED_ARTICLES: &str = "article.id IN (
+        SELECT article_id FROM article_tag_xref WHERE tag_id = ?
+        UNION
+        SELECT article_xref.article_id
+        FROM
+            feed_article_xref as article_xref
+            INNER JOIN feed_tag_xref as tag_xref ON tag_xref.feed_id = article_xref.feed_id
+        WHERE tag_xref.tag_id = ?
+    )";
 
-    pub fn close(mut self) -> Result<()> {
-        self.db.close()
+const ARTICLE_LIST_COLUMNS: &str = "article.id, (SELECT MIN(feed_id) FROM feed_article_xref WHERE article_id = article.id),
+    article.title, article.link, article.summary, article.author, article.published_at, article.is_read, article.is_starred";
+
+const ARTICLE_COLUMNS: &str = "article.id, article.guid, article.title, article.link, article.content, article.summary, article.pub_date, article.updated_date, article.published_at, article.updated_at, article.author, article.comments_url, article.is_read, article.read_at, article.is_starred, article.starred_at,
+    (SELECT MIN(feed_id) FROM feed_article_xref WHERE article_id = article.id), article.is_read_later, article.read_later_at";
+
+impl<'a> ArticleModel<'a> {
+    pub fn new(connection: &'a Connection) -> Self {
+        ArticleModel { connection }
     }
 
     pub fn _insert_article(self, article: Article) -> Result<Self> {
-        let connection = self
-            .db
-            .connection
-            .as_ref()
-            .ok_or(error!(ErrorType::Model("DB NOT OPEN".to_string())))?;
+        let connection = self.connection;
 
         connection
             .execute(
@@ -39,75 +100,671 @@ impl ArticleModel {
         Ok(self)
     }
 
-    pub fn insert_articles(self, feed_id: i64, articles: &Vec<Article>) -> Result<Self> {
-        let connection = self
-            .db
-            .connection
-            .as_ref()
-            .ok_or(error!(ErrorType::Model("DB NOT OPEN".to_string())))?;
+    ///Insert the articles that are new for this feed and update the ones whose content changed,
+    ///matching them on their guid. Returns the ids of the inserted articles and how many were updated
+    ///Articles saved before guids were stored have none, they are matched on their title and get one
+    ///Meant to run on a transaction so a feed is saved whole or not at all
+    pub fn upsert_articles(&self, feed_id: i64, articles: &Vec<Article>) -> Result<(Vec<i64>, usize)> {
+        let connection = self.connection;
 
+        let first_seen_at = chrono::Utc::now().timestamp();
+        let mut inserted = Vec::new();
+        let mut updated = 0;
         {
+            let mut existing_statement = connection
+                .prepare_cached(
+                    "SELECT
+                            article.id, article.hash
+                        FROM
+                            article
+                            INNER JOIN feed_article_xref as xref ON xref.article_id = article.id
+                        WHERE
+                            xref.feed_id = ?1
+                            AND (article.guid = ?2 OR (article.guid IS NULL AND article.title = ?3))
+                        ORDER BY
+                            article.guid IS NULL, article.id
+                        LIMIT 1
+                        ",
+                )
+                .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
             let mut article_statement = connection
-                .prepare("INSERT INTO article (title, content) VALUES (?1, ?2)")
+                .prepare_cached(
+                    "INSERT INTO article (guid, hash, title, link, content, summary, pub_date, updated_date, author, comments_url,
+                            published_at, updated_at, first_seen_at, content_text)
+                        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, COALESCE(?11, ?13), ?12, ?13, ?14)",
+                )
                 .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
-            let mut xref_statement = connection
-                .prepare("INSERT INTO feed_article_xref (feed_id, article_id) VALUES (?1, ?2)")
+            let mut update_statement = connection
+                .prepare_cached(
+                    "UPDATE article SET guid = ?1, hash = ?2, title = ?3, link = ?4, content = ?5, summary = ?6,
+                        pub_date = ?7, updated_date = ?8, author = ?9, comments_url = ?10,
+                        published_at = COALESCE(?11, published_at), updated_at = ?12, content_text = ?14
+                        WHERE id = ?13",
+                )
                 .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
-
-            // let transaction = connection.transaction().map_err(|e| Error::Model(e.to_string()))?;
-            connection
-                .execute("BEGIN TRANSACTION", [])
+            let mut xref_statement = connection
+                .prepare_cached("INSERT INTO feed_article_xref (feed_id, article_id) VALUES (?1, ?2)")
                 .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
 
             for article in articles {
-                let article_id = article_statement
-                    .insert([&article.title, &article.content])
-                    .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
-                xref_statement
-                    .execute((feed_id, article_id))
+                let hash = article.content_hash();
+                let existing = existing_statement
+                    .query_row((feed_id, &article.guid, &article.title), |row| {
+                        Ok((row.get::<_, i64>(0)?, row.get::<_, Option<String>>(1)?))
+                    })
+                    .optional()
                     .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+
+                match existing {
+                    Some((_, Some(existing_hash))) if existing_hash == hash => (),
+                    Some((article_id, _)) => {
+                        update_statement
+                            .execute(rusqlite::params![
+                                &article.guid,
+                                &hash,
+                                &article.title,
+                                &article.link,
+                                &article.content,
+                                &article.summary,
+                                &article.pub_date,
+                                &article.updated_date,
+                                &article.author,
+                                &article.comments_url,
+                                article.published_at,
+                                article.updated_at,
+                                article_id,
+                                plain_text(&article.content),
+                            ])
+                            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+                        self.set_categories(article_id, &article.categories)?;
+                        updated += 1;
+                    }
+                    None => {
+                        let article_id = article_statement
+                            .insert(rusqlite::params![
+                                &article.guid,
+                                &hash,
+                                &article.title,
+                                &article.link,
+                                &article.content,
+                                &article.summary,
+                                &article.pub_date,
+                                &article.updated_date,
+                                &article.author,
+                                &article.comments_url,
+                                article.published_at,
+                                article.updated_at,
+                                first_seen_at,
+                                plain_text(&article.content),
+                            ])
+                            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+                        xref_statement
+                            .execute((feed_id, article_id))
+                            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+                        self.set_categories(article_id, &article.categories)?;
+                        inserted.push(article_id);
+                    }
+                }
             }
-            connection
-                .execute("Commit", [])
+        }
+
+        Ok((inserted, updated))
+    }
+
+    ///Categories the feed gave the article, replacing the previous ones
+    fn set_categories(&self, article_id: i64, categories: &[String]) -> Result<()> {
+        let connection = self.connection;
+
+        connection
+            .prepare_cached("DELETE FROM article_category WHERE article_id = ?1")
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?
+            .execute([article_id])
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        let mut statement = connection
+            .prepare_cached("INSERT INTO article_category (article_id, name) VALUES (?1, ?2)")
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        for category in categories {
+            statement
+                .execute((article_id, category))
                 .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
-            // transaction.commit().map_err(|e| Error::Model(e.to_string()))?;
         }
+        Ok(())
+    }
 
-        Ok(self)
+    pub fn get_categories(&self, article_id: i32) -> Result<Vec<String>> {
+        let connection = self.connection;
+
+        let mut statement = connection
+            .prepare_cached("SELECT name FROM article_category WHERE article_id = ?1 ORDER BY rowid")
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        let rows = statement
+            .query_map([article_id], |row| row.get::<_, String>(0))
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+
+        let mut categories = Vec::new();
+        for row in rows {
+            categories.push(row.map_err(|e| error!(ErrorType::Model(e.to_string())))?);
+        }
+        Ok(categories)
+    }
+
+    fn row_to_article(row: &rusqlite::Row) -> rusqlite::Result<ArticleRow> {
+        Ok(ArticleRow {
+            id: row.get(0)?,
+            guid: row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+            title: row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+            link: row.get(3)?,
+            content: row.get::<_, Option<String>>(4)?.unwrap_or_default(),
+            summary: row.get(5)?,
+            pub_date: row.get(6)?,
+            updated_date: row.get(7)?,
+            published_at: row.get(8)?,
+            updated_at: row.get(9)?,
+            author: row.get(10)?,
+            comments_url: row.get(11)?,
+            is_read: row.get(12)?,
+            read_at: row.get(13)?,
+            is_starred: row.get(14)?,
+            starred_at: row.get(15)?,
+            feed_id: row.get(16)?,
+            is_read_later: row.get(17)?,
+            read_later_at: row.get(18)?,
+        })
+    }
+
+    fn row_to_list_item(row: &rusqlite::Row) -> rusqlite::Result<ArticleListRow> {
+        Ok(ArticleListRow {
+            id: row.get(0)?,
+            feed_id: row.get(1)?,
+            title: row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+            link: row.get(3)?,
+            summary: row.get(4)?,
+            author: row.get(5)?,
+            published_at: row.get(6)?,
+            is_read: row.get(7)?,
+            is_starred: row.get(8)?,
+        })
+    }
+
+    pub fn get_article(&self, article_id: i32) -> Result<ArticleRow> {
+        let connection = self.connection;
+
+        connection
+            .prepare_cached(&format!("SELECT {ARTICLE_COLUMNS} FROM article WHERE article.id = ?1"))
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?
+            .query_row([article_id], Self::row_to_article)
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))
     }
 
-    pub fn get_articles_by_feed(&self, feed_id: i32) -> Result<Vec<(i32, String, String)>> {
-        let connection = self
-            .db
-            .connection
-            .as_ref()
-            .ok_or(error!(ErrorType::Model("DB NOT OPEN".to_string())))?;
+    ///One page of a listing, starting after `after` when given
+    pub fn get_articles(
+        &self,
+        view: &ArticleView,
+        filter: &ArticleFilter,
+        sort: ArticleSort,
+        after: Option<&ArticleCursor>,
+        limit: u32,
+    ) -> Result<Vec<ArticleListRow>> {
+        let connection = self.connection;
+
+        let (with, condition, value) = match view {
+            ArticleView::Feed(feed_id) => (
+                "",
+                "article.id IN (SELECT article_id FROM feed_article_xref WHERE feed_id = ?1)",
+                Some(*feed_id as i64),
+            ),
+            ArticleView::Category(category_id) => (
+                CATEGORY_SUBTREE,
+                "article.id IN (
+                    SELECT article_xref.article_id
+                    FROM
+                        feed_article_xref as article_xref
+                        INNER JOIN feed_category_xref as category_xref ON category_xref.feed_id = article_xref.feed_id
+                    WHERE category_xref.category_id IN (SELECT id FROM subtree)
+                )",
+                Some(*category_id as i64),
+            ),
+            ArticleView::All => ("", "1", None),
+            ArticleView::Starred => ("", "article.is_starred = 1", None),
+            ArticleView::ReadLater => ("", "article.is_read_later = 1", None),
+            ArticleView::Hidden => ("", "article.is_hidden = 1", None),
+            ArticleView::Today(since) => ("", "article.published_at >= ?1", Some(*since)),
+            ArticleView::Search(compiled) => ("", compiled.condition.as_str(), None),
+        };
+        let mut conditions = vec![condition];
+        if !matches!(view, ArticleView::Hidden) {
+            conditions.push("article.is_hidden = 0");
+        }
+        let mut values: Vec<rusqlite::types::Value> = match view {
+            ArticleView::Search(compiled) => compiled.values.clone(),
+            _ => value.into_iter().map(rusqlite::types::Value::from).collect(),
+        };
+        if filter.unread_only {
+            conditions.push("article.is_read = 0");
+        }
+        if filter.starred_only {
+            conditions.push("article.is_starred = 1");
+        }
+        if let Some(tag_id) = filter.tag_id {
+            conditions.push(TAGGED_ARTICLES);
+            values.extend([(tag_id as i64).into(), (tag_id as i64).into()]);
+        }
+
+        // Keyset pagination, the cursor is compared with the sort key so pages don't shift
+        // when articles are added while scrolling
+        let order = match sort {
+            ArticleSort::Newest => "article.published_at DESC, article.id DESC",
+            ArticleSort::Oldest => "article.published_at ASC, article.id ASC",
+            ArticleSort::Inserted => "article.id ASC",
+        };
+        if let Some(after) = after {
+            match sort {
+                ArticleSort::Newest => {
+                    conditions.push("(article.published_at, article.id) < (?, ?)");
+                    values.extend([after.published_at.into(), (after.id as i64).into()]);
+                }
+                ArticleSort::Oldest => {
+                    conditions.push("(article.published_at, article.id) > (?, ?)");
+                    values.extend([after.published_at.into(), (after.id as i64).into()]);
+                }
+                ArticleSort::Inserted => {
+                    conditions.push("article.id > ?");
+                    values.push((after.id as i64).into());
+                }
+            }
+        }
+        values.push((limit as i64).into());
 
         let mut statement = connection
-            .prepare(
-                "SELECT
-                        *
+            .prepare_cached(&format!(
+                "{with}
+                    SELECT
+                        {ARTICLE_LIST_COLUMNS}
+                    FROM
+                        article
+                    WHERE
+                        {}
+                    ORDER BY
+                        {order}
+                    LIMIT ?
+                    ",
+                conditions.join(" AND ")
+            ))
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        let rows = statement
+            .query_map(rusqlite::params_from_iter(values), Self::row_to_list_item)
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+
+        let mut articles: Vec<ArticleListRow> = Vec::new();
+        for row in rows {
+            articles.push(row.map_err(|e| error!(ErrorType::Model(e.to_string())))?);
+        }
+        Ok(articles)
+    }
+
+    ///`condition` is a compiled search query and `values` its parameters
+    ///`highlight` is the FTS5 query ranking the results, they come newest first without it
+    pub fn search_articles(
+        &self,
+        condition: &str,
+        values: Vec<rusqlite::types::Value>,
+        highlight: Option<&str>,
+        filter: &ArticleSearchFilter,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<ArticleSearchRow>> {
+        let connection = self.connection;
+
+        // The subtree takes ?1, the other values follow in the order they appear in the query
+        let mut with = "";
+        let mut query_values: Vec<rusqlite::types::Value> = Vec::new();
+        let mut conditions = vec![condition, "article.is_hidden = 0"];
+        if let Some(category_id) = filter.category_id {
+            with = CATEGORY_SUBTREE;
+            query_values.push(category_id.into());
+            conditions.push(
+                "article.id IN (
+                    SELECT article_xref.article_id
+                    FROM
+                        feed_article_xref as article_xref
+                        INNER JOIN feed_category_xref as category_xref ON category_xref.feed_id = article_xref.feed_id
+                    WHERE category_xref.category_id IN (SELECT id FROM subtree)
+                )",
+            );
+        }
+        let (columns, matched, order) = match highlight {
+            Some(highlight) => {
+                query_values.push(highlight.to_string().into());
+                (
+                    "COALESCE(matched.title_highlight, article.title), COALESCE(matched.snippet, ''), COALESCE(matched.rank, 0.0)",
+                    format!(
+                        "LEFT JOIN (
+                            SELECT
+                                rowid,
+                                highlight(article_search, 0, char(2), char(3)) as title_highlight,
+                                snippet(article_search, 1, char(2), char(3), '…', 24) as snippet,
+                                {SEARCH_RANK} as rank
+                            FROM article_search
+                            WHERE article_search MATCH ?
+                        ) as matched ON matched.rowid = article.id"
+                    ),
+                    "matched.rank IS NULL, matched.rank, article.published_at DESC, article.id DESC",
+                )
+            }
+            None => (
+                "article.title, '', 0.0",
+                String::new(),
+                "article.published_at DESC, article.id DESC",
+            ),
+        };
+        query_values.extend(values);
+        if let Some(feed_id) = filter.feed_id {
+            conditions.push("article.id IN (SELECT article_id FROM feed_article_xref WHERE feed_id = ?)");
+            query_values.push(feed_id.into());
+        }
+        if let Some(since) = filter.since {
+            conditions.push("article.published_at >= ?");
+            query_values.push(since.into());
+        }
+        if let Some(until) = filter.until {
+            conditions.push("article.published_at < ?");
+            query_values.push(until.into());
+        }
+        if let Some(is_read) = filter.is_read {
+            conditions.push("article.is_read = ?");
+            query_values.push(is_read.into());
+        }
+        query_values.extend([(limit as i64).into(), (offset as i64).into()]);
+
+        let mut statement = connection
+            .prepare_cached(&format!(
+                "{with}
+                    SELECT
+                        {ARTICLE_LIST_COLUMNS},
+                        {columns}
                     FROM
                         article
-                        LEFT JOIN feed_article_xref as xref ON xref.article_id = article.id
+                        {matched}
                     WHERE
-                        xref.feed_id = ?1
+                        {}
                     ORDER BY
-                        `id` ASC
+                        {order}
+                    LIMIT ? OFFSET ?
+                    ",
+                conditions.join(" AND ")
+            ))
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        let rows = statement
+            .query_map(rusqlite::params_from_iter(query_values), |row| {
+                Ok(ArticleSearchRow {
+                    article: Self::row_to_list_item(row)?,
+                    title_highlight: row.get::<_, Option<String>>(9)?.unwrap_or_default(),
+                    snippet: row.get::<_, Option<String>>(10)?.unwrap_or_default(),
+                    rank: row.get(11)?,
+                })
+            })
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+
+        let mut articles = Vec::new();
+        for row in rows {
+            articles.push(row.map_err(|e| error!(ErrorType::Model(e.to_string())))?);
+        }
+        Ok(articles)
+    }
+
+    ///Unread articles per feed, feeds without any are left out
+    pub fn get_feed_unread_counts(&self) -> Result<Vec<(i32, i64)>> {
+        let connection = self.connection;
+
+        let mut statement = connection
+            .prepare_cached(
+                "SELECT
+                        xref.feed_id, COUNT(*)
+                    FROM
+                        feed_article_xref as xref
+                        INNER JOIN article ON article.id = xref.article_id
+                    WHERE
+                        article.is_read = 0 AND article.is_hidden = 0
+                    GROUP BY
+                        xref.feed_id
+                    ",
+            )
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        let rows = statement
+            .query_map([], |row| Ok((row.get::<_, i32>(0)?, row.get::<_, i64>(1)?)))
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+
+        let mut counts = Vec::new();
+        for row in rows {
+            counts.push(row.map_err(|e| error!(ErrorType::Model(e.to_string())))?);
+        }
+        Ok(counts)
+    }
+
+    ///Unread articles per category, including the ones of the feeds in its sub categories
+    ///An article is only counted once even if its feed is in several categories of the subtree
+    pub fn get_category_unread_counts(&self) -> Result<Vec<(i32, i64)>> {
+        let connection = self.connection;
+
+        let mut statement = connection
+            .prepare_cached(
+                "WITH RECURSIVE ancestry(category_id, ancestor_id) AS (
+                        SELECT id, id FROM category
+                        UNION
+                        SELECT ancestry.category_id, category.parent_id
+                        FROM ancestry INNER JOIN category ON category.id = ancestry.ancestor_id
+                        WHERE category.parent_id IS NOT NULL
+                    )
+                    SELECT
+                        ancestry.ancestor_id, COUNT(DISTINCT article.id)
+                    FROM
+                        ancestry
+                        INNER JOIN feed_category_xref as category_xref ON category_xref.category_id = ancestry.category_id
+                        INNER JOIN feed_article_xref as article_xref ON article_xref.feed_id = category_xref.feed_id
+                        INNER JOIN article ON article.id = article_xref.article_id
+                    WHERE
+                        article.is_read = 0 AND article.is_hidden = 0
+                    GROUP BY
+                        ancestry.ancestor_id
                     ",
             )
             .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
         let rows = statement
-            .query_map([feed_id], |row| {
-                Ok((
-                    row.get::<_, i32>(0)?,
-                    row.get::<_, String>(1)?,
-                    row.get::<_, String>(2)?,
-                ))
+            .query_map([], |row| Ok((row.get::<_, i32>(0)?, row.get::<_, i64>(1)?)))
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+
+        let mut counts = Vec::new();
+        for row in rows {
+            counts.push(row.map_err(|e| error!(ErrorType::Model(e.to_string())))?);
+        }
+        Ok(counts)
+    }
+
+    pub fn get_unread_count(&self) -> Result<i64> {
+        let connection = self.connection;
+
+        connection
+            .prepare_cached("SELECT COUNT(*) FROM article WHERE is_read = 0 AND is_hidden = 0")
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?
+            .query_row([], |row| row.get(0))
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))
+    }
+
+    ///Unread articles matching a compiled search query
+    pub fn get_search_unread_count(&self, compiled: &CompiledQuery) -> Result<i64> {
+        let connection = self.connection;
+
+        connection
+            .prepare_cached(&format!(
+                "SELECT COUNT(*) FROM article WHERE article.is_read = 0 AND article.is_hidden = 0 AND {}",
+                compiled.condition
+            ))
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?
+            .query_row(rusqlite::params_from_iter(compiled.values.iter()), |row| row.get(0))
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))
+    }
+
+    ///read_at keeps when the article was read, it's cleared when marked unread again
+    pub fn update_read(&self, article_ids: &[i32], is_read: bool, now: i64) -> Result<usize> {
+        let connection = self.connection;
+
+        let mut statement = connection
+            .prepare_cached(
+                "UPDATE article SET is_read = ?1, read_at = CASE WHEN ?1 THEN COALESCE(read_at, ?2) END
+                    WHERE id = ?3 AND is_read != ?1",
+            )
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        let mut updated = 0;
+        for article_id in article_ids {
+            updated += statement
+                .execute((is_read, now, article_id))
+                .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        }
+        Ok(updated)
+    }
+
+    pub fn update_feed_read(&self, feed_id: i32, is_read: bool, now: i64) -> Result<usize> {
+        let connection = self.connection;
+
+        connection
+            .prepare_cached(
+                "UPDATE article SET is_read = ?2, read_at = CASE WHEN ?2 THEN COALESCE(read_at, ?3) END
+                    WHERE is_read != ?2
+                        AND id IN (SELECT article_id FROM feed_article_xref WHERE feed_id = ?1)",
+            )
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?
+            .execute((feed_id, is_read, now))
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))
+    }
+
+    ///Every article of the feeds in the category and its sub categories
+    pub fn update_category_read(&self, category_id: i32, is_read: bool, now: i64) -> Result<usize> {
+        let connection = self.connection;
+
+        connection
+            .prepare_cached(&format!(
+                "{CATEGORY_SUBTREE}
+                    UPDATE article SET is_read = ?2, read_at = CASE WHEN ?2 THEN COALESCE(read_at, ?3) END
+                        WHERE is_read != ?2
+                            AND id IN (
+                                SELECT article_xref.article_id
+                                FROM
+                                    feed_article_xref as article_xref
+                                    INNER JOIN feed_category_xref as category_xref ON category_xref.feed_id = article_xref.feed_id
+                                WHERE category_xref.category_id IN (SELECT id FROM subtree)
+                            )",
+            ))
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?
+            .execute((category_id, is_read, now))
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))
+    }
+
+    pub fn update_starred(&self, article_ids: &[i32], is_starred: bool, now: i64) -> Result<usize> {
+        let connection = self.connection;
+
+        let mut statement = connection
+            .prepare_cached(
+                "UPDATE article SET is_starred = ?1, starred_at = CASE WHEN ?1 THEN ?2 END
+                    WHERE id = ?3 AND is_starred != ?1",
+            )
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        let mut updated = 0;
+        for article_id in article_ids {
+            updated += statement
+                .execute((is_starred, now, article_id))
+                .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        }
+        Ok(updated)
+    }
+
+    ///Hidden articles stay in the database so the feed doesn't bring them back on the next refresh
+    pub fn update_hidden(&self, article_ids: &[i32], is_hidden: bool) -> Result<usize> {
+        let connection = self.connection;
+
+        let mut statement = connection
+            .prepare_cached("UPDATE article SET is_hidden = ?1 WHERE id = ?2 AND is_hidden != ?1")
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        let mut updated = 0;
+        for article_id in article_ids {
+            updated += statement
+                .execute((is_hidden, article_id))
+                .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        }
+        Ok(updated)
+    }
+
+    pub fn update_read_later(&self, article_ids: &[i32], is_read_later: bool, now: i64) -> Result<usize> {
+        let connection = self.connection;
+
+        let mut statement = connection
+            .prepare_cached(
+                "UPDATE article SET is_read_later = ?1, read_later_at = CASE WHEN ?1 THEN ?2 END
+                    WHERE id = ?3 AND is_read_later != ?1",
+            )
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        let mut updated = 0;
+        for article_id in article_ids {
+            updated += statement
+                .execute((is_read_later, now, article_id))
+                .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        }
+        Ok(updated)
+    }
+
+    ///Articles for the rules to look at, newest first, hidden ones are left out
+    ///Either the given articles or, with None, the ones of `feed_ids` (every feed when None too)
+    pub fn get_rule_candidates(
+        &self,
+        article_ids: Option<&[i64]>,
+        feed_ids: Option<&[i32]>,
+    ) -> Result<Vec<RuleCandidateRow>> {
+        let connection = self.connection;
+
+        let (condition, values): (String, Vec<i64>) = match (article_ids, feed_ids) {
+            (Some(article_ids), _) => (
+                format!("article.id IN ({})", vec!["?"; article_ids.len()].join(", ")),
+                article_ids.to_vec(),
+            ),
+            (None, Some(feed_ids)) => (
+                format!(
+                    "article.id IN (SELECT article_id FROM feed_article_xref WHERE feed_id IN ({}))",
+                    vec!["?"; feed_ids.len()].join(", ")
+                ),
+                feed_ids.iter().map(|feed_id| *feed_id as i64).collect(),
+            ),
+            (None, None) => ("1".to_string(), Vec::new()),
+        };
+
+        // The id lists vary in length, the statement isn't worth caching
+        let mut statement = connection
+            .prepare(&format!(
+                "SELECT
+                        {ARTICLE_LIST_COLUMNS},
+                        article.content,
+                        (SELECT group_concat(name, char(10)) FROM article_category WHERE article_id = article.id)
+                    FROM
+                        article
+                    WHERE
+                        {condition} AND article.is_hidden = 0
+                    ORDER BY
+                        article.published_at DESC, article.id DESC
+                    "
+            ))
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        let rows = statement
+            .query_map(rusqlite::params_from_iter(values), |row| {
+                Ok(RuleCandidateRow {
+                    article: Self::row_to_list_item(row)?,
+                    content: row.get::<_, Option<String>>(9)?.unwrap_or_default(),
+                    categories: row
+                        .get::<_, Option<String>>(10)?
+                        .map(|categories| categories.split('\n').map(str::to_string).collect())
+                        .unwrap_or_default(),
+                })
             })
             .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
 
-        let mut articles: Vec<(i32, String, String)> = Vec::new();
+        let mut articles = Vec::new();
         for row in rows {
             articles.push(row.map_err(|e| error!(ErrorType::Model(e.to_string())))?);
         }
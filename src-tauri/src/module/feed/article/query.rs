@@ -0,0 +1,671 @@
+use chrono::{Local, NaiveDate, TimeZone};
+use rusqlite::types::Value;
+
+use crate::error;
+use crate::shared::errors::*;
+
+///Search query language, e.g. `feed:"Rust Blog" title:release -is:read after:2026-01-01`
+///
+///```text
+///query   = or
+///or      = and ("OR" and)*
+///and     = unary (["AND"] unary)*
+///unary   = ("NOT" | "-") unary | primary
+///primary = "(" or ")" | field ":" (word | phrase) | word | phrase
+///```
+///Positions in errors are character offsets in the query
+#[derive(Debug, Clone, PartialEq)]
+pub(in crate::module::feed) enum QueryExpr {
+    And(Vec<QueryExpr>),
+    Or(Vec<QueryExpr>),
+    Not(Box<QueryExpr>),
+    Term(QueryTerm),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(in crate::module::feed) enum QueryTerm {
+    ///Title, content or author
+    Text(TextMatch),
+    Title(TextMatch),
+    Author(TextMatch),
+    Content(TextMatch),
+    ///Part of the feed title
+    Feed(String),
+    ///Part of the category title, the categories below it included
+    Category(String),
+    ///Whole tag name, on the article or its feed
+    Tag(String),
+    Read(bool),
+    Starred(bool),
+    ///Published at or after, unix timestamp
+    After(i64),
+    ///Published before, unix timestamp
+    Before(i64),
+}
+
+///A phrase matches its words in order, a word also matches the words it starts
+#[derive(Debug, Clone, PartialEq)]
+pub(in crate::module::feed) struct TextMatch {
+    pub value: String,
+    pub phrase: bool,
+}
+
+///WHERE condition on `article` and its parameters
+#[derive(Debug)]
+pub struct CompiledQuery {
+    pub condition: String,
+    pub values: Vec<Value>,
+    ///FTS5 query of the text searched for, to rank and highlight the results
+    pub highlight: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    Open,
+    Close,
+    And,
+    Or,
+    Not,
+    Word(String),
+    Phrase(String),
+    ///Name, value, value was quoted, where the value starts
+    Field(String, String, bool, usize),
+}
+
+#[derive(Debug)]
+struct Token {
+    kind: TokenKind,
+    start: usize,
+    end: usize,
+}
+
+fn parse_error(message: &str, start: usize, end: usize) -> Error {
+    error!(ErrorType::QueryParse(message.to_string(), start, end))
+}
+
+///None for a query without any term
+pub(in crate::module::feed) fn parse(query: &str) -> Result<Option<QueryExpr>> {
+    let chars: Vec<char> = query.chars().collect();
+    let tokens = tokenize(&chars)?;
+    if tokens.is_empty() {
+        return Ok(None);
+    }
+
+    let mut parser = Parser {
+        tokens,
+        position: 0,
+        length: chars.len(),
+    };
+    let expr = parser.parse_or()?;
+    if let Some(token) = parser.peek() {
+        return Err(parse_error("unexpected closing parenthesis", token.start, token.end));
+    }
+    Ok(Some(expr))
+}
+
+fn tokenize(chars: &[char]) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        match c {
+            '(' => {
+                i += 1;
+                tokens.push(Token { kind: TokenKind::Open, start, end: i });
+            }
+            ')' => {
+                i += 1;
+                tokens.push(Token { kind: TokenKind::Close, start, end: i });
+            }
+            '"' => {
+                let (phrase, end) = read_phrase(chars, i)?;
+                i = end;
+                tokens.push(Token { kind: TokenKind::Phrase(phrase), start, end: i });
+            }
+            // A dash glued to a term negates it, anywhere else it is part of a word
+            '-' if chars.get(i + 1).is_some_and(|next| !next.is_whitespace() && *next != ')') => {
+                i += 1;
+                tokens.push(Token { kind: TokenKind::Not, start, end: i });
+            }
+            _ => {
+                while i < chars.len() && !chars[i].is_whitespace() && !matches!(chars[i], '(' | ')' | '"') {
+                    i += 1;
+                }
+                let word: String = chars[start..i].iter().collect();
+                let kind = match word.as_str() {
+                    "AND" => TokenKind::And,
+                    "OR" => TokenKind::Or,
+                    "NOT" => TokenKind::Not,
+                    _ => match word.split_once(':') {
+                        // Addresses are searched as text
+                        Some((name, value))
+                            if !name.is_empty()
+                                && name.chars().all(|c| c.is_ascii_alphabetic())
+                                && !value.starts_with("//") =>
+                        {
+                            let value_start = start + name.chars().count() + 1;
+                            if value.is_empty() && chars.get(i) == Some(&'"') {
+                                let (phrase, end) = read_phrase(chars, i)?;
+                                i = end;
+                                TokenKind::Field(name.to_ascii_lowercase(), phrase, true, value_start)
+                            } else {
+                                TokenKind::Field(name.to_ascii_lowercase(), value.to_string(), false, value_start)
+                            }
+                        }
+                        _ => TokenKind::Word(word),
+                    },
+                };
+                tokens.push(Token { kind, start, end: i });
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+///Phrase starting at the quote at `start`, and the position after the closing quote
+fn read_phrase(chars: &[char], start: usize) -> Result<(String, usize)> {
+    match chars[start + 1..].iter().position(|c| *c == '"') {
+        Some(length) => Ok((
+            chars[start + 1..start + 1 + length].iter().collect(),
+            start + length + 2,
+        )),
+        None => Err(parse_error("missing closing quote", start, chars.len())),
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    length: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<&Token> {
+        self.position += 1;
+        self.tokens.get(self.position - 1)
+    }
+
+    ///Error when nothing that can start a term follows the token at `start..end`
+    fn expect_term(&self, what: &str, start: usize, end: usize) -> Result<()> {
+        match self.peek().map(|token| &token.kind) {
+            None | Some(TokenKind::Close | TokenKind::And | TokenKind::Or) => {
+                Err(parse_error(&format!("expected a search term after {what}"), start, end))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<QueryExpr> {
+        let mut items = vec![self.parse_and()?];
+        while let Some(Token { kind: TokenKind::Or, start, end }) = self.peek() {
+            let (start, end) = (*start, *end);
+            self.position += 1;
+            self.expect_term("OR", start, end)?;
+            items.push(self.parse_and()?);
+        }
+        Ok(match items.len() {
+            1 => items.remove(0),
+            _ => QueryExpr::Or(items),
+        })
+    }
+
+    fn parse_and(&mut self) -> Result<QueryExpr> {
+        let mut items = vec![self.parse_unary()?];
+        loop {
+            match self.peek() {
+                None | Some(Token { kind: TokenKind::Close | TokenKind::Or, .. }) => break,
+                Some(Token { kind: TokenKind::And, start, end }) => {
+                    let (start, end) = (*start, *end);
+                    self.position += 1;
+                    self.expect_term("AND", start, end)?;
+                }
+                _ => {}
+            }
+            items.push(self.parse_unary()?);
+        }
+        Ok(match items.len() {
+            1 => items.remove(0),
+            _ => QueryExpr::And(items),
+        })
+    }
+
+    fn parse_unary(&mut self) -> Result<QueryExpr> {
+        if let Some(Token { kind: TokenKind::Not, start, end }) = self.peek() {
+            let (start, end) = (*start, *end);
+            self.position += 1;
+            self.expect_term("NOT", start, end)?;
+            return Ok(QueryExpr::Not(Box::new(self.parse_unary()?)));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<QueryExpr> {
+        let length = self.length;
+        let Some(token) = self.next() else {
+            return Err(parse_error("expected a search term", length, length));
+        };
+        let (start, end) = (token.start, token.end);
+        match token.kind.clone() {
+            TokenKind::Open => {
+                if let Some(Token { kind: TokenKind::Close, end, .. }) = self.peek() {
+                    return Err(parse_error("empty parentheses", start, *end));
+                }
+                let expr = self.parse_or()?;
+                match self.next() {
+                    Some(Token { kind: TokenKind::Close, .. }) => Ok(expr),
+                    _ => Err(parse_error("missing closing parenthesis", start, end)),
+                }
+            }
+            TokenKind::Close => Err(parse_error("unexpected closing parenthesis", start, end)),
+            TokenKind::And | TokenKind::Or => {
+                Err(parse_error("expected a search term before the operator", start, end))
+            }
+            TokenKind::Not => Err(parse_error("expected a search term", start, end)),
+            TokenKind::Word(value) => Ok(QueryExpr::Term(QueryTerm::Text(TextMatch {
+                value,
+                phrase: false,
+            }))),
+            TokenKind::Phrase(value) if value.trim().is_empty() => {
+                Err(parse_error("empty quotes", start, end))
+            }
+            TokenKind::Phrase(value) => Ok(QueryExpr::Term(QueryTerm::Text(TextMatch {
+                value,
+                phrase: true,
+            }))),
+            TokenKind::Field(name, value, phrase, value_start) => {
+                field_term(&name, value, phrase, start, value_start, end).map(QueryExpr::Term)
+            }
+        }
+    }
+}
+
+fn field_term(
+    name: &str,
+    value: String,
+    phrase: bool,
+    start: usize,
+    value_start: usize,
+    end: usize,
+) -> Result<QueryTerm> {
+    if value.trim().is_empty() {
+        return Err(parse_error(&format!("missing value after {name}:"), start, end));
+    }
+    let text = TextMatch { value: value.clone(), phrase };
+    match name {
+        "title" => Ok(QueryTerm::Title(text)),
+        "author" => Ok(QueryTerm::Author(text)),
+        "content" => Ok(QueryTerm::Content(text)),
+        "feed" => Ok(QueryTerm::Feed(value)),
+        "category" => Ok(QueryTerm::Category(value)),
+        "tag" => Ok(QueryTerm::Tag(value.trim().to_string())),
+        "is" => match value.to_ascii_lowercase().as_str() {
+            "read" => Ok(QueryTerm::Read(true)),
+            "unread" => Ok(QueryTerm::Read(false)),
+            "starred" => Ok(QueryTerm::Starred(true)),
+            "unstarred" => Ok(QueryTerm::Starred(false)),
+            _ => Err(parse_error(
+                "expected read, unread, starred or unstarred",
+                value_start,
+                end,
+            )),
+        },
+        "after" | "before" => {
+            let midnight = NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d")
+                .ok()
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+                .and_then(|midnight| Local.from_local_datetime(&midnight).earliest())
+                .ok_or(parse_error("expected a date like 2026-01-31", value_start, end))?;
+            Ok(match name {
+                "after" => QueryTerm::After(midnight.timestamp()),
+                _ => QueryTerm::Before(midnight.timestamp()),
+            })
+        }
+        _ => Err(parse_error(&format!("unknown field {name}:"), start, value_start)),
+    }
+}
+
+pub(in crate::module::feed) fn compile(expr: &QueryExpr) -> CompiledQuery {
+    let mut values = Vec::new();
+    let condition = condition(expr, &mut values);
+
+    let mut highlights = Vec::new();
+    collect_highlights(expr, &mut highlights);
+    CompiledQuery {
+        condition,
+        values,
+        highlight: match highlights.is_empty() {
+            true => None,
+            false => Some(highlights.join(" OR ")),
+        },
+    }
+}
+
+fn condition(expr: &QueryExpr, values: &mut Vec<Value>) -> String {
+    match expr {
+        QueryExpr::And(items) => format!(
+            "({})",
+            items.iter().map(|item| condition(item, values)).collect::<Vec<_>>().join(" AND ")
+        ),
+        QueryExpr::Or(items) => format!(
+            "({})",
+            items.iter().map(|item| condition(item, values)).collect::<Vec<_>>().join(" OR ")
+        ),
+        QueryExpr::Not(item) => format!("NOT {}", condition(item, values)),
+        QueryExpr::Term(term) => term_condition(term, values),
+    }
+}
+
+fn term_condition(term: &QueryTerm, values: &mut Vec<Value>) -> String {
+    let text_search = "article.id IN (SELECT rowid FROM article_search WHERE article_search MATCH ?)";
+    match term {
+        QueryTerm::Text(text) => {
+            values.push(fts_match(None, text).into());
+            text_search.to_string()
+        }
+        QueryTerm::Title(text) => {
+            values.push(fts_match(Some("title"), text).into());
+            text_search.to_string()
+        }
+        QueryTerm::Author(text) => {
+            values.push(fts_match(Some("author"), text).into());
+            text_search.to_string()
+        }
+        QueryTerm::Content(text) => {
+            values.push(fts_match(Some("content_text"), text).into());
+            text_search.to_string()
+        }
+        QueryTerm::Feed(title) => {
+            values.push(like_pattern(title).into());
+            "article.id IN (
+                SELECT article_xref.article_id
+                FROM
+                    feed_article_xref as article_xref
+                    INNER JOIN feed ON feed.id = article_xref.feed_id
+                WHERE feed.title LIKE ? ESCAPE '\\'
+            )"
+            .to_string()
+        }
+        QueryTerm::Category(title) => {
+            values.push(like_pattern(title).into());
+            "article.id IN (
+                WITH RECURSIVE matched_category(id) AS (
+                    SELECT id FROM category WHERE title LIKE ? ESCAPE '\\'
+                    UNION
+                    SELECT category.id FROM category INNER JOIN matched_category ON category.parent_id = matched_category.id
+                )
+                SELECT article_xref.article_id
+                FROM
+                    feed_article_xref as article_xref
+                    INNER JOIN feed_category_xref as category_xref ON category_xref.feed_id = article_xref.feed_id
+                WHERE category_xref.category_id IN (SELECT id FROM matched_category)
+            )"
+            .to_string()
+        }
+        QueryTerm::Tag(name) => {
+            values.push(name.clone().into());
+            "article.id IN (
+                WITH matched_tag(id) AS (SELECT id FROM tag WHERE name = ?)
+                SELECT article_id FROM article_tag_xref WHERE tag_id IN (SELECT id FROM matched_tag)
+                UNION
+                SELECT article_xref.article_id
+                FROM
+                    feed_article_xref as article_xref
+                    INNER JOIN feed_tag_xref as tag_xref ON tag_xref.feed_id = article_xref.feed_id
+                WHERE tag_xref.tag_id IN (SELECT id FROM matched_tag)
+            )"
+            .to_string()
+        }
+        QueryTerm::Read(is_read) => {
+            values.push((*is_read).into());
+            "article.is_read = ?".to_string()
+        }
+        QueryTerm::Starred(is_starred) => {
+            values.push((*is_starred).into());
+            "article.is_starred = ?".to_string()
+        }
+        QueryTerm::After(since) => {
+            values.push((*since).into());
+            "article.published_at >= ?".to_string()
+        }
+        QueryTerm::Before(until) => {
+            values.push((*until).into());
+            "article.published_at < ?".to_string()
+        }
+    }
+}
+
+///Text searched for outside of a NOT
+fn collect_highlights(expr: &QueryExpr, highlights: &mut Vec<String>) {
+    match expr {
+        QueryExpr::And(items) | QueryExpr::Or(items) => {
+            for item in items {
+                collect_highlights(item, highlights);
+            }
+        }
+        QueryExpr::Not(_) => {}
+        QueryExpr::Term(QueryTerm::Text(text)) => highlights.push(fts_match(None, text)),
+        QueryExpr::Term(QueryTerm::Title(text)) => highlights.push(fts_match(Some("title"), text)),
+        QueryExpr::Term(QueryTerm::Author(text)) => highlights.push(fts_match(Some("author"), text)),
+        QueryExpr::Term(QueryTerm::Content(text)) => {
+            highlights.push(fts_match(Some("content_text"), text))
+        }
+        QueryExpr::Term(_) => {}
+    }
+}
+
+///Quoted so FTS5 operators are searched as text
+fn fts_match(column: Option<&str>, text: &TextMatch) -> String {
+    let quoted = format!("\"{}\"", text.value.replace('"', "\"\""));
+    let quoted = match text.phrase {
+        true => quoted,
+        false => format!("{quoted}*"),
+    };
+    match column {
+        Some(column) => format!("{column} : {quoted}"),
+        None => quoted,
+    }
+}
+
+fn like_pattern(value: &str) -> String {
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{escaped}%")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(value: &str, phrase: bool) -> TextMatch {
+        TextMatch {
+            value: value.to_string(),
+            phrase,
+        }
+    }
+
+    ///Message and character range of a query that doesn't parse
+    fn parse_failure(query: &str) -> (String, usize, usize) {
+        match parse(query).expect_err(query).kind() {
+            ErrorType::QueryParse(message, start, end) => (message.clone(), *start, *end),
+            other => panic!("{query}: unexpected error {other:?}"),
+        }
+    }
+
+    #[test]
+    fn parses_words_and_fields() {
+        assert_eq!(parse("").unwrap(), None);
+        assert_eq!(parse("   ").unwrap(), None);
+        assert_eq!(
+            parse("rust").unwrap(),
+            Some(QueryExpr::Term(QueryTerm::Text(text("rust", false))))
+        );
+        assert_eq!(
+            parse(r#"title:"release notes" -is:read tag:work"#).unwrap(),
+            Some(QueryExpr::And(vec![
+                QueryExpr::Term(QueryTerm::Title(text("release notes", true))),
+                QueryExpr::Not(Box::new(QueryExpr::Term(QueryTerm::Read(true)))),
+                QueryExpr::Term(QueryTerm::Tag("work".to_string())),
+            ]))
+        );
+        // Addresses and dashes inside words are text
+        assert_eq!(
+            parse("https://example.com well-known").unwrap(),
+            Some(QueryExpr::And(vec![
+                QueryExpr::Term(QueryTerm::Text(text("https://example.com", false))),
+                QueryExpr::Term(QueryTerm::Text(text("well-known", false))),
+            ]))
+        );
+    }
+
+    #[test]
+    fn parses_operators() {
+        let word = |value: &str| QueryExpr::Term(QueryTerm::Text(text(value, false)));
+        assert_eq!(
+            parse("a b OR c").unwrap(),
+            Some(QueryExpr::Or(vec![
+                QueryExpr::And(vec![word("a"), word("b")]),
+                word("c")
+            ]))
+        );
+        assert_eq!(
+            parse("a AND (b OR c)").unwrap(),
+            Some(QueryExpr::And(vec![
+                word("a"),
+                QueryExpr::Or(vec![word("b"), word("c")])
+            ]))
+        );
+        assert_eq!(
+            parse("NOT is:starred").unwrap(),
+            Some(QueryExpr::Not(Box::new(QueryExpr::Term(
+                QueryTerm::Starred(true)
+            ))))
+        );
+    }
+
+    #[test]
+    fn parses_dates_as_local_midnight() {
+        let midnight = Local
+            .from_local_datetime(
+                &NaiveDate::from_ymd_opt(2026, 1, 31)
+                    .unwrap()
+                    .and_hms_opt(0, 0, 0)
+                    .unwrap(),
+            )
+            .earliest()
+            .unwrap()
+            .timestamp();
+        assert_eq!(
+            parse("after:2026-01-31").unwrap(),
+            Some(QueryExpr::Term(QueryTerm::After(midnight)))
+        );
+        assert_eq!(
+            parse("before:2026-01-31").unwrap(),
+            Some(QueryExpr::Term(QueryTerm::Before(midnight)))
+        );
+    }
+
+    #[test]
+    fn reports_field_errors_at_the_field() {
+        assert_eq!(
+            parse_failure("rust colour:red"),
+            ("unknown field colour:".to_string(), 5, 12)
+        );
+        assert_eq!(
+            parse_failure("rust title:"),
+            ("missing value after title:".to_string(), 5, 11)
+        );
+        assert_eq!(
+            parse_failure("rust is:later"),
+            (
+                "expected read, unread, starred or unstarred".to_string(),
+                8,
+                13
+            )
+        );
+        assert_eq!(
+            parse_failure("after:2026-13-01 rust"),
+            ("expected a date like 2026-01-31".to_string(), 6, 16)
+        );
+    }
+
+    #[test]
+    fn reports_syntax_errors() {
+        assert_eq!(
+            parse_failure("rust )"),
+            ("unexpected closing parenthesis".to_string(), 5, 6)
+        );
+        assert_eq!(
+            parse_failure("(rust"),
+            ("missing closing parenthesis".to_string(), 0, 1)
+        );
+        assert_eq!(
+            parse_failure("rust ()"),
+            ("empty parentheses".to_string(), 5, 7)
+        );
+        assert_eq!(
+            parse_failure(r#"rust "release"#),
+            ("missing closing quote".to_string(), 5, 13)
+        );
+        assert_eq!(
+            parse_failure(r#"rust "  ""#),
+            ("empty quotes".to_string(), 5, 9)
+        );
+        assert_eq!(
+            parse_failure("rust OR"),
+            ("expected a search term after OR".to_string(), 5, 7)
+        );
+        assert_eq!(
+            parse_failure("OR rust"),
+            (
+                "expected a search term before the operator".to_string(),
+                0,
+                2
+            )
+        );
+    }
+
+    #[test]
+    fn reports_offsets_in_characters() {
+        // "café" is five bytes but four characters
+        assert_eq!(
+            parse_failure("café is:later"),
+            (
+                "expected read, unread, starred or unstarred".to_string(),
+                8,
+                13
+            )
+        );
+        assert_eq!(
+            parse_failure("日本語 )"),
+            ("unexpected closing parenthesis".to_string(), 4, 5)
+        );
+    }
+
+    #[test]
+    fn compiles_parameters_in_order() {
+        let compiled = compile(&parse("title:rust feed:blog is:unread").unwrap().unwrap());
+        assert_eq!(compiled.values.len(), 3);
+        assert!(compiled.condition.contains("article_search MATCH ?"));
+        assert!(compiled.condition.contains("feed.title LIKE ?"));
+        assert_eq!(compiled.values[1], Value::Text("%blog%".to_string()));
+        assert_eq!(compiled.values[2], Value::Integer(0));
+        assert!(compiled.highlight.is_some());
+
+        // Nothing to highlight without text terms
+        let compiled = compile(&parse("is:starred").unwrap().unwrap());
+        assert_eq!(compiled.condition, "article.is_starred = ?");
+        assert_eq!(compiled.highlight, None);
+    }
+}
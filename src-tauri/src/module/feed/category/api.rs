@@ -1,17 +1,55 @@
 // use crate::error;
+use crate::shared::database::Db;
 use crate::shared::errors::*;
 
 use super::controller::CategoryController;
-use super::entities::Category;
+use super::entities::{Category, CategoryDeleteMode, CategoryTree};
+
+use tauri::State;
 
 #[tauri::command]
-pub async fn add_category(title: String, parent_id: Option<i32>) -> Result<()> {
-    CategoryController::new().add(title, parent_id).await?;
+pub async fn add_category(db: State<'_, Db>, title: String, parent_id: Option<i32>) -> Result<()> {
+    CategoryController::new(db.inner().clone()).add(title, parent_id).await?;
     Ok(())
 }
 
 #[tauri::command]
-pub async fn get_all_categories() -> Result<Vec<Category>> {
-    let categories = CategoryController::new().get_all()?;
+pub async fn get_all_categories(db: State<'_, Db>) -> Result<Vec<Category>> {
+    let categories = CategoryController::new(db.inner().clone()).get_all().await?;
     Ok(categories)
-}
\ No newline at end of file
+}
+
+///Categories nested under their parent, each with its feeds, plus the feeds in no category
+#[tauri::command]
+pub async fn get_category_tree(db: State<'_, Db>) -> Result<CategoryTree> {
+    let tree = CategoryController::new(db.inner().clone()).get_tree().await?;
+    Ok(tree)
+}
+
+#[tauri::command]
+pub async fn rename_category(db: State<'_, Db>, category_id: i32, title: String) -> Result<()> {
+    CategoryController::new(db.inner().clone()).rename(category_id, title).await?;
+    Ok(())
+}
+
+///`parent_id` None moves the category to the top level
+#[tauri::command]
+pub async fn move_category(db: State<'_, Db>, category_id: i32, parent_id: Option<i32>) -> Result<()> {
+    CategoryController::new(db.inner().clone())
+        .set_parent(category_id, parent_id)
+        .await?;
+    Ok(())
+}
+
+///Returns the number of feeds deleted along with the category
+#[tauri::command]
+pub async fn delete_category(
+    db: State<'_, Db>,
+    category_id: i32,
+    mode: Option<CategoryDeleteMode>,
+) -> Result<usize> {
+    let deleted_feeds = CategoryController::new(db.inner().clone())
+        .delete(category_id, mode.unwrap_or_default())
+        .await?;
+    Ok(deleted_feeds)
+}
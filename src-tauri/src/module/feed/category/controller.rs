@@ -1,14 +1,25 @@
-// use crate::error;
+use std::collections::HashMap;
+
+use crate::error;
+use crate::shared::database::Db;
 use crate::shared::errors::*;
 
-use super::entities::Category;
+use super::super::controller::FeedController;
+use super::super::entities::Feed;
+use super::super::model::FeedModel;
+use super::entities::{Category, CategoryDeleteMode, CategoryTree};
 use super::model::CategoryModel;
 
-pub(super) struct CategoryController {}
+///Id, parent id and title of the categories under one parent
+type CategoryChildren = HashMap<i32, Vec<(i32, Option<i32>, String)>>;
+
+pub(super) struct CategoryController {
+    db: Db,
+}
 
 impl CategoryController {
-    pub fn new() -> Self {
-        CategoryController {}
+    pub fn new(db: Db) -> Self {
+        CategoryController { db }
     }
 
     pub async fn add(&self, title: String, parent_id: Option<i32>) -> Result<()> {
@@ -16,19 +27,25 @@ impl CategoryController {
             Some(parent_id) => Category::builder().title(title).parent(parent_id).build(),
             None => Category::builder().title(title).build(),
         };
-        CategoryModel::new()
-            .open()?
-            .insert_category(category)?
-            .close()?;
-        Ok(())
+        self.db
+            .transaction(move |transaction| {
+                let category_model = CategoryModel::new(transaction);
+                if let Some(parent_id) = parent_id {
+                    category_model.get_category(parent_id)?;
+                }
+                category_model.insert_category(category)?;
+                Ok(())
+            })
+            .await
     }
 
-    pub fn get_all(&self) -> Result<Vec<Category>> {
-        let model = CategoryModel::new().open()?;
+    pub async fn get_all(&self) -> Result<Vec<Category>> {
         let mut categories = Vec::new();
 
-        let model_categories = model.get_categories()?;
-        model.close()?;
+        let model_categories = self
+            .db
+            .call(|connection| CategoryModel::new(connection).get_categories())
+            .await?;
         let _: Vec<_> = model_categories
             .into_iter()
             .map(|i| match i.1 {
@@ -40,4 +57,122 @@ impl CategoryController {
             .collect();
         Ok(categories)
     }
+
+    ///Nested categories, each with its feeds
+    pub async fn get_tree(&self) -> Result<CategoryTree> {
+        let (model_categories, model_feeds) = self
+            .db
+            .call(|connection| {
+                Ok((
+                    CategoryModel::new(connection).get_category_tree()?,
+                    FeedModel::new(connection).get_feeds()?,
+                ))
+            })
+            .await?;
+
+        let mut uncategorized = Vec::new();
+        let mut feeds_by_category: HashMap<i32, Vec<Feed>> = HashMap::new();
+        for feed_row in model_feeds {
+            if feed_row.category_ids.is_empty() {
+                uncategorized.push(FeedController::feed_from_row(feed_row));
+                continue;
+            }
+            // A feed in several categories shows up in each of them
+            for category_id in &feed_row.category_ids {
+                feeds_by_category
+                    .entry(*category_id)
+                    .or_default()
+                    .push(FeedController::feed_from_row(feed_row.clone()));
+            }
+        }
+
+        let mut roots = Vec::new();
+        let mut children_by_parent: CategoryChildren = HashMap::new();
+        for (id, parent_id, title, depth) in model_categories {
+            match parent_id {
+                Some(parent_id) if depth > 0 => {
+                    children_by_parent.entry(parent_id).or_default().push((id, Some(parent_id), title))
+                }
+                _ => roots.push((id, parent_id, title)),
+            }
+        }
+
+        let categories = roots
+            .into_iter()
+            .map(|root| Self::build_node(root, &mut children_by_parent, &mut feeds_by_category))
+            .collect();
+        Ok(CategoryTree {
+            categories,
+            feeds: uncategorized,
+        })
+    }
+
+    fn build_node(
+        (id, parent_id, title): (i32, Option<i32>, String),
+        children_by_parent: &mut CategoryChildren,
+        feeds_by_category: &mut HashMap<i32, Vec<Feed>>,
+    ) -> Category {
+        let children = children_by_parent
+            .remove(&id)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|child| Self::build_node(child, children_by_parent, feeds_by_category))
+            .collect();
+        let builder = Category::builder()
+            .id(id)
+            .title(title)
+            .feeds(feeds_by_category.remove(&id).unwrap_or_default())
+            .children(children);
+        match parent_id {
+            Some(parent_id) => builder.parent(parent_id).build(),
+            None => builder.build(),
+        }
+    }
+
+    pub async fn rename(&self, category_id: i32, title: String) -> Result<()> {
+        self.db
+            .call(move |connection| CategoryModel::new(connection).update_title(category_id, &title))
+            .await
+    }
+
+    ///None moves the category to the top level. A category can't go below itself
+    pub async fn set_parent(&self, category_id: i32, parent_id: Option<i32>) -> Result<()> {
+        self.db
+            .transaction(move |transaction| {
+                let category_model = CategoryModel::new(transaction);
+                category_model.get_category(category_id)?;
+                if let Some(parent_id) = parent_id {
+                    category_model.get_category(parent_id)?;
+                    if category_model.get_subtree_ids(category_id)?.contains(&parent_id) {
+                        return Err(error!(ErrorType::CategoryCycle(category_id, parent_id)));
+                    }
+                }
+                category_model.update_parent(category_id, parent_id)
+            })
+            .await
+    }
+
+    ///Returns the number of feeds deleted with the category
+    pub async fn delete(&self, category_id: i32, mode: CategoryDeleteMode) -> Result<usize> {
+        self.db
+            .transaction(move |transaction| {
+                let category_model = CategoryModel::new(transaction);
+                match mode {
+                    CategoryDeleteMode::MoveUp => {
+                        category_model.delete_category_move_up(category_id)?;
+                        Ok(0)
+                    }
+                    CategoryDeleteMode::DeleteAll => {
+                        let feed_ids = category_model.get_subtree_only_feed_ids(category_id)?;
+                        category_model.delete_subtree(category_id)?;
+                        let feed_model = FeedModel::new(transaction);
+                        for feed_id in &feed_ids {
+                            feed_model.delete_feed(*feed_id)?;
+                        }
+                        Ok(feed_ids.len())
+                    }
+                }
+            })
+            .await
+    }
 }
@@ -8,6 +8,25 @@ pub struct Category {
     pub(super) title: String,
     pub(super) parent_id: Option<i32>,
     pub(super) feeds: Option<Vec<Feed>>,
+    pub(super) children: Option<Vec<Category>>,
+}
+
+///Whole category tree, with the feeds that aren't in any category
+#[derive(Debug, Serialize)]
+pub struct CategoryTree {
+    pub(super) categories: Vec<Category>,
+    pub(super) feeds: Vec<Feed>,
+}
+
+///What happens to the sub-categories and feeds of a deleted category
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CategoryDeleteMode {
+    ///They go to the parent of the deleted category, or to the top level
+    #[default]
+    MoveUp,
+    ///They are deleted too, feeds that are also in another category are kept
+    DeleteAll,
 }
 
 pub(super) struct CategoryBuilder {
@@ -15,6 +34,7 @@ pub(super) struct CategoryBuilder {
     title: Option<String>,
     parent_id: Option<i32>,
     feeds: Option<Vec<Feed>>,
+    children: Option<Vec<Category>>,
 }
 
 impl Category {
@@ -24,6 +44,7 @@ impl Category {
             title: None,
             parent_id: None,
             feeds: None,
+            children: None,
         }
     }
 }
@@ -44,17 +65,23 @@ impl CategoryBuilder {
         self
     }
 
-    pub fn _feeds(mut self, feeds: Vec<Feed>) -> Self {
+    pub fn feeds(mut self, feeds: Vec<Feed>) -> Self {
         self.feeds = Some(feeds);
         self
     }
 
+    pub fn children(mut self, children: Vec<Category>) -> Self {
+        self.children = Some(children);
+        self
+    }
+
     pub fn build(self) -> Category {
         Category {
             id: self.id.unwrap_or(0),
             title: self.title.unwrap_or("".to_string()),
             parent_id: self.parent_id,
             feeds: self.feeds,
+            children: self.children,
         }
     }
 }
@@ -1,53 +1,70 @@
 use crate::error;
-use crate::shared::database::{Db, IDb};
 use crate::shared::errors::*;
+use rusqlite::{Connection, OptionalExtension};
 
 use super::entities::Category;
 
-pub(super) struct CategoryModel {
-    db: Db,
+///Ids of category ?1 and all the categories below it, as the `subtree` table
+pub(in crate::module::feed) const CATEGORY_SUBTREE: &str = "WITH RECURSIVE subtree(id) AS (
+        SELECT id FROM category WHERE id = ?1
+        UNION
+        SELECT category.id FROM category INNER JOIN subtree ON category.parent_id = subtree.id
+    )";
+
+///Id, parent id, title and depth, 0 for the top level
+pub(in crate::module::feed) type CategoryTreeRow = (i32, Option<i32>, String, i64);
+
+pub(in crate::module::feed) struct CategoryModel<'a> {
+    connection: &'a Connection,
 }
 
-impl CategoryModel {
-    pub fn new() -> Self {
-        CategoryModel { db: Db::new() }
+impl<'a> CategoryModel<'a> {
+    pub fn new(connection: &'a Connection) -> Self {
+        CategoryModel { connection }
     }
 
-    pub fn open(mut self) -> Result<Self> {
-        self.db.open()?;
-        Ok(self)
-    }
+    pub fn insert_category(&self, category: Category) -> Result<i64> {
+        let connection = self.connection;
 
-    pub fn close(mut self) -> Result<()> {
-        self.db.close()
+        connection
+            .execute(
+                "INSERT INTO category (title, parent_id) VALUES (?1, ?2)",
+                (category.title, category.parent_id),
+            )
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        let category_id = connection.last_insert_rowid();
+        Ok(category_id)
     }
 
-    pub fn insert_category(self, category: Category) -> Result<Self> {
-        let connection = self
-            .db
-            .connection
-            .as_ref()
-            .ok_or(error!(ErrorType::Model("DB NOT OPEN".to_string())))?;
+    ///Id of the category with that title under that parent, created when there is none
+    pub fn get_or_insert_category(&self, title: &str, parent_id: Option<i32>) -> Result<i32> {
+        let connection = self.connection;
+
+        let mut statement = connection
+            .prepare_cached("SELECT id FROM category WHERE title = ?1 AND parent_id IS ?2 ORDER BY id LIMIT 1")
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        let existing = statement
+            .query_row((title, parent_id), |row| row.get::<_, i32>(0))
+            .optional()
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        if let Some(category_id) = existing {
+            return Ok(category_id);
+        }
 
         connection
             .execute(
                 "INSERT INTO category (title, parent_id) VALUES (?1, ?2)",
-                (category.title, category.parent_id),
+                (title, parent_id),
             )
             .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
-        // let category_id = connection.last_insert_rowid();
-        Ok(self)
+        Ok(connection.last_insert_rowid() as i32)
     }
 
     pub fn get_categories(&self) -> Result<Vec<(i32, Option<i32>, String)>> {
-        let connection = self
-            .db
-            .connection
-            .as_ref()
-            .ok_or(error!(ErrorType::Model("DB NOT OPEN".to_string())))?;
+        let connection = self.connection;
 
         let mut statement = connection
-            .prepare("SELECT * FROM category ORDER BY COALESCE(parent_id, id), title;")
+            .prepare_cached("SELECT * FROM category ORDER BY COALESCE(parent_id, id), title;")
             .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
 
         let rows = statement
@@ -66,4 +83,193 @@ impl CategoryModel {
         }
         Ok(categories)
     }
+
+    pub fn get_category(&self, category_id: i32) -> Result<(i32, Option<i32>, String)> {
+        let connection = self.connection;
+
+        let mut statement = connection
+            .prepare_cached("SELECT id, parent_id, title FROM category WHERE id = ?1")
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        statement
+            .query_row([category_id], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .map_err(|e| match e {
+                rusqlite::Error::QueryReturnedNoRows => {
+                    error!(ErrorType::NotFound(format!("category {category_id}")))
+                }
+                e => error!(ErrorType::Model(e.to_string())),
+            })
+    }
+
+    ///Every category with its depth, roots first then by title
+    ///Categories whose parent is gone count as roots, the ones caught in a loop are left out
+    pub fn get_category_tree(&self) -> Result<Vec<CategoryTreeRow>> {
+        let connection = self.connection;
+
+        let mut statement = connection
+            .prepare_cached(
+                "WITH RECURSIVE tree(id, parent_id, title, depth, path) AS (
+                    SELECT id, parent_id, title, 0, '/' || id || '/' FROM category
+                        WHERE parent_id IS NULL OR parent_id NOT IN (SELECT id FROM category)
+                    UNION ALL
+                    SELECT category.id, category.parent_id, category.title, tree.depth + 1, tree.path || category.id || '/'
+                        FROM category INNER JOIN tree ON category.parent_id = tree.id
+                        WHERE instr(tree.path, '/' || category.id || '/') = 0
+                )
+                SELECT id, parent_id, title, depth FROM tree ORDER BY depth, title COLLATE NOCASE, id",
+            )
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+
+        let rows = statement
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+
+        let mut categories = Vec::new();
+        for row in rows {
+            categories.push(row.map_err(|e| error!(ErrorType::Model(e.to_string())))?);
+        }
+        Ok(categories)
+    }
+
+    ///Ids of the category and everything below it
+    pub fn get_subtree_ids(&self, category_id: i32) -> Result<Vec<i32>> {
+        let connection = self.connection;
+
+        let mut statement = connection
+            .prepare_cached(&format!("{CATEGORY_SUBTREE} SELECT id FROM subtree"))
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        let rows = statement
+            .query_map([category_id], |row| row.get::<_, i32>(0))
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+
+        let mut ids = Vec::new();
+        for row in rows {
+            ids.push(row.map_err(|e| error!(ErrorType::Model(e.to_string())))?);
+        }
+        Ok(ids)
+    }
+
+    ///Feeds of the category or below it
+    pub fn get_subtree_feed_ids(&self, category_id: i32) -> Result<Vec<i32>> {
+        let connection = self.connection;
+
+        let mut statement = connection
+            .prepare_cached(&format!(
+                "{CATEGORY_SUBTREE}
+                SELECT DISTINCT feed_id FROM feed_category_xref WHERE category_id IN (SELECT id FROM subtree)"
+            ))
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        let rows = statement
+            .query_map([category_id], |row| row.get::<_, i32>(0))
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+
+        let mut ids = Vec::new();
+        for row in rows {
+            ids.push(row.map_err(|e| error!(ErrorType::Model(e.to_string())))?);
+        }
+        Ok(ids)
+    }
+
+    ///Feeds of the category or below it that aren't in any other category
+    pub fn get_subtree_only_feed_ids(&self, category_id: i32) -> Result<Vec<i32>> {
+        let connection = self.connection;
+
+        let mut statement = connection
+            .prepare_cached(&format!(
+                "{CATEGORY_SUBTREE}
+                SELECT DISTINCT feed_id FROM feed_category_xref
+                    WHERE category_id IN (SELECT id FROM subtree)
+                        AND feed_id NOT IN (
+                            SELECT feed_id FROM feed_category_xref WHERE category_id NOT IN (SELECT id FROM subtree)
+                        )"
+            ))
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        let rows = statement
+            .query_map([category_id], |row| row.get::<_, i32>(0))
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+
+        let mut ids = Vec::new();
+        for row in rows {
+            ids.push(row.map_err(|e| error!(ErrorType::Model(e.to_string())))?);
+        }
+        Ok(ids)
+    }
+
+    pub fn update_title(&self, category_id: i32, title: &str) -> Result<()> {
+        let connection = self.connection;
+
+        let updated = connection
+            .execute("UPDATE category SET title = ?1 WHERE id = ?2", (title, category_id))
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        if updated == 0 {
+            return Err(error!(ErrorType::NotFound(format!("category {category_id}"))));
+        }
+        Ok(())
+    }
+
+    ///Doesn't check for cycles, see `get_subtree_ids`
+    pub fn update_parent(&self, category_id: i32, parent_id: Option<i32>) -> Result<()> {
+        let connection = self.connection;
+
+        let updated = connection
+            .execute("UPDATE category SET parent_id = ?1 WHERE id = ?2", (parent_id, category_id))
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        if updated == 0 {
+            return Err(error!(ErrorType::NotFound(format!("category {category_id}"))));
+        }
+        Ok(())
+    }
+
+    ///Hand the sub-categories and feeds of the category to its parent, then delete it. Meant to run on a transaction
+    pub fn delete_category_move_up(&self, category_id: i32) -> Result<()> {
+        let connection = self.connection;
+
+        let (_, parent_id, _) = self.get_category(category_id)?;
+        connection
+            .execute(
+                "UPDATE category SET parent_id = ?1 WHERE parent_id = ?2",
+                (parent_id, category_id),
+            )
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        if let Some(parent_id) = parent_id {
+            connection
+                .execute(
+                    "INSERT INTO feed_category_xref (feed_id, category_id)
+                        SELECT DISTINCT feed_id, ?1 FROM feed_category_xref
+                            WHERE category_id = ?2
+                                AND feed_id NOT IN (SELECT feed_id FROM feed_category_xref WHERE category_id = ?1)",
+                    (parent_id, category_id),
+                )
+                .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        }
+        for query in [
+            "DELETE FROM feed_category_xref WHERE category_id = ?1",
+            "DELETE FROM category WHERE id = ?1",
+        ] {
+            connection
+                .execute(query, [category_id])
+                .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        }
+        Ok(())
+    }
+
+    ///Delete the category and everything below it, feeds are left alone. Meant to run on a transaction
+    pub fn delete_subtree(&self, category_id: i32) -> Result<()> {
+        let connection = self.connection;
+
+        let ids = self.get_subtree_ids(category_id)?;
+        if ids.is_empty() {
+            return Err(error!(ErrorType::NotFound(format!("category {category_id}"))));
+        }
+        for id in ids {
+            for query in [
+                "DELETE FROM feed_category_xref WHERE category_id = ?1",
+                "DELETE FROM category WHERE id = ?1",
+            ] {
+                connection
+                    .execute(query, [id])
+                    .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+            }
+        }
+        Ok(())
+    }
 }
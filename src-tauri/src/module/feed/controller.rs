@@ -1,66 +1,457 @@
-// use crate::error;
+use chrono::Local;
+
+use crate::error;
+use crate::service::cron::CronSchedule;
+use crate::service::http::{CacheValidators, HttpReqwest, IHttp};
+use crate::shared::database::Db;
 use crate::shared::errors::*;
-use crate::shared::types::Url;
+use crate::shared::types::{FeedCandidate, Url};
 
 use super::article::model::ArticleModel;
-use super::entities::Feed;
-use super::model::FeedModel;
-pub(super) struct FeedController {}
+use super::discovery;
+use super::entities::{Feed, FeedUrlChange, RefreshResult};
+use super::model::{FeedModel, FeedRow};
+use super::rule::engine::RuleEngine;
+use super::rule::entities::ArticleNotification;
+use super::scheduler;
+pub(super) struct FeedController {
+    db: Db,
+}
 
 impl FeedController {
-    pub fn new() -> Self {
-        FeedController {}
+    pub fn new(db: Db) -> Self {
+        FeedController { db }
     }
 
     ///Get feed without saving it to the DB
     pub async fn fetch(url: Url) -> Result<Feed> {
-        Feed::from_url(url).await
+        Feed::from_url(url).await.map(|(feed, _)| feed)
     }
 
-    ///Add feed to db
-    pub async fn add(&self, url: Url, title: String, category_id: Option<i32>) -> Result<()> {
-        let mut feed = Feed::from_url(url).await?;
+    ///Feeds available at an url, either the feed itself or the ones a web page links to
+    pub async fn discover(url: Url) -> Result<Vec<FeedCandidate>> {
+        let response = HttpReqwest {}.fetch(&url).await?;
+
+        if let Ok(feed) = Feed::parse(&response.body, response.header("content-type")) {
+            return Ok(vec![FeedCandidate {
+                url: response.permanent_url().cloned().unwrap_or(url),
+                title: Some(feed.title),
+                feed_type: response.header("content-type").map(str::to_string),
+            }]);
+        }
+        if !discovery::is_html(&response) {
+            return Err(error!(ErrorType::XmlBadFormat));
+        }
+        Ok(discovery::discover(&response).await)
+    }
+
+    ///Add feed to db, returns what the rules want notified about its articles
+    pub async fn add(
+        &self,
+        url: Url,
+        title: String,
+        category_id: Option<i32>,
+    ) -> Result<Vec<ArticleNotification>> {
+        let (mut feed, response) = Feed::from_url(url.clone()).await?;
         feed.title = title;
+        // Same as a refresh: the move is recorded and the next fetch can be conditional
+        let moved = match response.permanent_url() {
+            Some(new_url) if *new_url != url => Some((new_url.to_owned(), response.redirects[0].status)),
+            _ => None,
+        };
+        let validators = response.validators();
 
         if let Some(category_id) = category_id {
-            feed.category_id = Some(category_id)
+            feed.category_ids = vec![category_id]
+        }
+
+        self.db
+            .transaction(move |transaction| {
+                let feed_model = FeedModel::new(transaction);
+                let feed_id = feed_model.insert_feed(&feed)?;
+                let now = Local::now();
+                let next_fetch_at = scheduler::next_fetch_at(
+                    None,
+                    None,
+                    feed_model.get_default_refresh_interval()?,
+                    now,
+                );
+                feed_model.update_fetch_times(feed_id as i32, now.timestamp(), next_fetch_at)?;
+                if let Some((new_url, status)) = moved {
+                    feed_model.update_xml_url(feed_id as i32, &url, &new_url, status, now.timestamp())?;
+                }
+                feed_model.update_validators(feed_id as i32, &validators)?;
+
+                let (inserted, _) = ArticleModel::new(transaction).upsert_articles(feed_id, &feed.articles)?;
+                RuleEngine::load(transaction)?.run(transaction, &inserted, now.timestamp())
+            })
+            .await
+    }
+
+    ///Fetch a subscribed feed again and save the articles that are new or changed
+    pub async fn refresh(&self, feed_id: i32) -> Result<RefreshResult> {
+        let (feed_row, default_interval) = self
+            .db
+            .call(move |connection| {
+                let feed_model = FeedModel::new(connection);
+                Ok((
+                    feed_model.get_feed(feed_id)?,
+                    feed_model.get_default_refresh_interval()?,
+                ))
+            })
+            .await?;
+
+        let validators = CacheValidators {
+            etag: feed_row.etag,
+            last_modified: feed_row.last_modified,
+        };
+        let result = self.fetch_articles(feed_id, feed_row.xml_url, validators).await;
+
+        // Reschedule even on failure so a broken feed isn't retried on every tick
+        let now = Local::now();
+        let mut next_fetch_at = scheduler::next_fetch_at(
+            feed_row.refresh_interval,
+            feed_row.refresh_cron.as_deref(),
+            default_interval,
+            now,
+        );
+        let (error_count, last_error, is_dead) = match &result {
+            Ok(_) => (0, None, false),
+            Err(e) if matches!(e.kind(), ErrorType::HttpGone) => {
+                (feed_row.error_count + 1, Some(format!("{:?}", e.kind())), true)
+            }
+            Err(e) => {
+                let error_count = feed_row.error_count + 1;
+                let retry_after = match e.kind() {
+                    ErrorType::HttpTooManyRequests(retry_after)
+                    | ErrorType::HttpServerError(_, retry_after) => *retry_after,
+                    _ => None,
+                };
+                next_fetch_at = scheduler::backoff_fetch_at(
+                    next_fetch_at,
+                    now.timestamp(),
+                    error_count,
+                    retry_after,
+                );
+                (error_count, Some(format!("{:?}", e.kind())), feed_row.is_dead)
+            }
+        };
+        let was_dead = feed_row.is_dead;
+        self.db
+            .transaction(move |transaction| {
+                let feed_model = FeedModel::new(transaction);
+                feed_model.update_error(feed_id, error_count, last_error)?;
+                if is_dead != was_dead {
+                    feed_model.update_dead(feed_id, is_dead)?;
+                }
+                feed_model.update_fetch_times(feed_id, now.timestamp(), next_fetch_at)
+            })
+            .await?;
+
+        result
+    }
+
+    async fn fetch_articles(
+        &self,
+        feed_id: i32,
+        xml_url: Url,
+        validators: CacheValidators,
+    ) -> Result<RefreshResult> {
+        let response = HttpReqwest {}
+            .fetch_conditional(&xml_url, &validators)
+            .await?;
+
+        let moved_to = match response.permanent_url() {
+            Some(new_url) if *new_url != xml_url => {
+                let new_url = new_url.to_owned();
+                let status = response.redirects[0].status;
+                let moved_to = new_url.clone();
+                self.db
+                    .transaction(move |transaction| {
+                        FeedModel::new(transaction).update_xml_url(
+                            feed_id,
+                            &xml_url,
+                            &new_url,
+                            status,
+                            Local::now().timestamp(),
+                        )
+                    })
+                    .await?;
+                Some(moved_to)
+            }
+            _ => None,
+        };
+
+        if response.is_not_modified() {
+            return Ok(RefreshResult {
+                feed_id,
+                inserted: 0,
+                updated: 0,
+                not_modified: true,
+                moved_to,
+                error: None,
+                notifications: Vec::new(),
+            });
         }
 
-        let feed_model = FeedModel::new().open()?;
-        let feed_id = feed_model.insert_feed(&feed)?;
-        feed_model.close()?;
+        let feed = Feed::parse(&response.body, response.header("content-type"))?;
 
-        ArticleModel::new()
-            .open()?
-            .insert_articles(feed_id, &feed.articles)?
-            .close()?;
+        // The validators are saved with the content, otherwise a 304 could hide it
+        let validators = response.validators();
+        let (inserted, updated, notifications) = self
+            .db
+            .transaction(move |transaction| {
+                let (inserted, updated) = ArticleModel::new(transaction)
+                    .upsert_articles(feed_id as i64, &feed.articles)?;
+                let notifications = RuleEngine::load(transaction)?.run(
+                    transaction,
+                    &inserted,
+                    Local::now().timestamp(),
+                )?;
+                let feed_model = FeedModel::new(transaction);
+                feed_model.update_metadata(feed_id, &feed)?;
+                feed_model.update_validators(feed_id, &validators)?;
+                Ok((inserted.len(), updated, notifications))
+            })
+            .await?;
 
-        Ok(())
+        Ok(RefreshResult {
+            feed_id,
+            inserted,
+            updated,
+            not_modified: false,
+            moved_to,
+            error: None,
+            notifications,
+        })
     }
 
-    pub fn get_all(&self) -> Result<Vec<Feed>> {
-        let model = FeedModel::new().open()?;
-        let mut feeds = Vec::new();
+    ///Refresh every subscribed feed, a failing feed doesn't stop the others
+    pub async fn refresh_all(&self) -> Result<Vec<RefreshResult>> {
+        let feed_ids = self
+            .db
+            .call(|connection| FeedModel::new(connection).get_feed_ids())
+            .await?;
+
+        self.refresh_many(feed_ids).await
+    }
 
-        let model_feeds = model.get_feeds()?;
-        model.close()?;
+    ///Refresh the feeds whose next fetch is due, including the ones missed while the app was closed
+    pub async fn refresh_due(&self) -> Result<Vec<RefreshResult>> {
+        let now = Local::now().timestamp();
+        let feed_ids = self
+            .db
+            .call(move |connection| FeedModel::new(connection).get_due_feed_ids(now))
+            .await?;
+
+        self.refresh_many(feed_ids).await
+    }
+
+    async fn refresh_many(&self, feed_ids: Vec<i32>) -> Result<Vec<RefreshResult>> {
+        let mut results = Vec::new();
+        for feed_id in feed_ids {
+            let result = match self.refresh(feed_id).await {
+                Ok(result) => result,
+                Err(e) => RefreshResult {
+                    feed_id,
+                    inserted: 0,
+                    updated: 0,
+                    not_modified: false,
+                    moved_to: None,
+                    error: Some(e.to_string()),
+                    notifications: Vec::new(),
+                },
+            };
+            results.push(result);
+        }
+        Ok(results)
+    }
+
+    ///Override the refresh interval (minutes) and/or cron expression of a feed, None falls back to the default
+    pub async fn set_schedule(
+        &self,
+        feed_id: i32,
+        refresh_interval: Option<i64>,
+        refresh_cron: Option<String>,
+    ) -> Result<()> {
+        if let Some(refresh_interval) = refresh_interval {
+            if refresh_interval <= 0 {
+                return Err(error!(ErrorType::Custom(
+                    "refresh interval must be positive".to_string()
+                )));
+            }
+        }
+        if let Some(refresh_cron) = &refresh_cron {
+            CronSchedule::parse(refresh_cron)?;
+        }
 
-        let _: Vec<_> = model_feeds
+        self.db
+            .transaction(move |transaction| {
+                let feed_model = FeedModel::new(transaction);
+                let feed_row = feed_model.get_feed(feed_id)?;
+                let default_interval = feed_model.get_default_refresh_interval()?;
+
+                let now = Local::now();
+                let last_fetched_at = feed_row
+                    .last_fetched_at
+                    .and_then(|timestamp| chrono::DateTime::from_timestamp(timestamp, 0))
+                    .map(|date| date.with_timezone(&Local))
+                    .unwrap_or(now);
+                let next_fetch_at = scheduler::next_fetch_at(
+                    refresh_interval,
+                    refresh_cron.as_deref(),
+                    default_interval,
+                    last_fetched_at,
+                );
+
+                feed_model.update_schedule(feed_id, refresh_interval, refresh_cron, next_fetch_at)
+            })
+            .await
+    }
+
+    pub async fn get_default_refresh_interval(&self) -> Result<i64> {
+        self.db
+            .call(|connection| FeedModel::new(connection).get_default_refresh_interval())
+            .await
+    }
+
+    pub async fn set_default_refresh_interval(&self, minutes: i64) -> Result<()> {
+        if minutes <= 0 {
+            return Err(error!(ErrorType::Custom(
+                "refresh interval must be positive".to_string()
+            )));
+        }
+        self.db
+            .call(move |connection| FeedModel::new(connection).set_default_refresh_interval(minutes))
+            .await
+    }
+
+    ///Past locations of a feed that moved with a permanent redirect, newest first
+    pub async fn get_url_history(&self, feed_id: i32) -> Result<Vec<FeedUrlChange>> {
+        let changes = self
+            .db
+            .call(move |connection| FeedModel::new(connection).get_url_history(feed_id))
+            .await?;
+
+        Ok(changes
             .into_iter()
-            .map(|i| {
-                let feed = match i.3 {
-                    Some(category_id) => Feed::builder()
-                        .id(i.0)
-                        .title(i.1)
-                        .xml_url(i.2)
-                        .category_id(category_id)
-                        .build(),
-                    None => Feed::builder().id(i.0).title(i.1).xml_url(i.2).build(),
-                };
-                feeds.push(feed);
+            .map(|(old_url, new_url, status, changed_at)| FeedUrlChange {
+                old_url,
+                new_url,
+                status,
+                changed_at,
             })
-            .collect();
+            .collect())
+    }
+
+    pub async fn get_all(&self) -> Result<Vec<Feed>> {
+        let model_feeds = self
+            .db
+            .call(|connection| FeedModel::new(connection).get_feeds())
+            .await?;
+
+        Ok(model_feeds.into_iter().map(Self::feed_from_row).collect())
+    }
+
+    pub(super) fn feed_from_row(i: FeedRow) -> Feed {
+        Feed::builder()
+            .id(i.id)
+            .title(i.title)
+            .xml_url(i.xml_url)
+            .link(i.link)
+            .description(i.description)
+            .language(i.language)
+            .image_url(i.image_url)
+            .generator(i.generator)
+            .last_build_at(i.last_build_at)
+            .refresh_interval(i.refresh_interval)
+            .refresh_cron(i.refresh_cron)
+            .last_fetched_at(i.last_fetched_at)
+            .next_fetch_at(i.next_fetch_at)
+            .error_count(i.error_count)
+            .last_error(i.last_error)
+            .dead(i.is_dead)
+            .category_ids(i.category_ids)
+            .tag_ids(i.tag_ids)
+            .build()
+    }
+
+    ///Rename the feed and/or point it to another address
+    pub async fn update(&self, feed_id: i32, title: Option<String>, xml_url: Option<Url>) -> Result<()> {
+        if let Some(xml_url) = &xml_url {
+            url::Url::parse(xml_url).map_err(|e| error!(ErrorType::ReqwestBadUrl(e.to_string())))?;
+        }
+        self.db
+            .transaction(move |transaction| {
+                let feed_model = FeedModel::new(transaction);
+                if let Some(title) = title {
+                    feed_model.update_title(feed_id, &title)?;
+                }
+                if let Some(xml_url) = xml_url {
+                    feed_model.update_url(feed_id, &xml_url)?;
+                }
+                Ok(())
+            })
+            .await
+    }
+
+    pub async fn set_categories(&self, feed_id: i32, category_ids: Vec<i32>) -> Result<()> {
+        self.db
+            .transaction(move |transaction| {
+                let feed_model = FeedModel::new(transaction);
+                feed_model.get_feed(feed_id)?;
+                feed_model.set_categories(feed_id, &category_ids)
+            })
+            .await
+    }
+
+    pub async fn add_to_category(&self, feed_id: i32, category_id: i32) -> Result<()> {
+        self.db
+            .transaction(move |transaction| {
+                let feed_model = FeedModel::new(transaction);
+                feed_model.get_feed(feed_id)?;
+                feed_model.add_category(feed_id, category_id)
+            })
+            .await
+    }
+
+    pub async fn remove_from_category(&self, feed_id: i32, category_id: i32) -> Result<()> {
+        self.db
+            .call(move |connection| FeedModel::new(connection).remove_category(feed_id, category_id))
+            .await
+    }
+
+    ///Move the feed out of `from` (or every category when None) into `to` (or no category when None)
+    pub async fn move_to_category(
+        &self,
+        feed_id: i32,
+        from: Option<i32>,
+        to: Option<i32>,
+    ) -> Result<()> {
+        self.db
+            .transaction(move |transaction| {
+                let feed_model = FeedModel::new(transaction);
+                let feed_row = feed_model.get_feed(feed_id)?;
+                match from {
+                    Some(from) => feed_model.remove_category(feed_id, from)?,
+                    None => {
+                        for category_id in feed_row.category_ids {
+                            feed_model.remove_category(feed_id, category_id)?;
+                        }
+                    }
+                }
+                if let Some(to) = to {
+                    feed_model.add_category(feed_id, to)?;
+                }
+                Ok(())
+            })
+            .await
+    }
 
-        Ok(feeds)
+    ///Unsubscribe, returns how many articles went with the feed
+    pub async fn delete(&self, feed_id: i32) -> Result<usize> {
+        self.db
+            .transaction(move |transaction| FeedModel::new(transaction).delete_feed(feed_id))
+            .await
     }
 }
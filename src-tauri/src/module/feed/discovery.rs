@@ -0,0 +1,169 @@
+use regex::Regex;
+use std::sync::LazyLock;
+use std::time::Duration;
+use tokio::task::JoinSet;
+
+use crate::service::http::{HttpReqwest, HttpResponse};
+use crate::shared::types::{FeedCandidate, Url};
+
+use super::entities::Feed;
+
+const FEED_TYPES: [&str; 4] = [
+    "application/rss+xml",
+    "application/atom+xml",
+    "application/feed+json",
+    "application/json",
+];
+
+///Paths tried on the site root when the page doesn't advertise any feed
+const COMMON_PATHS: [&str; 7] = [
+    "/feed",
+    "/rss.xml",
+    "/atom.xml",
+    "/feed.xml",
+    "/index.xml",
+    "/rss",
+    "/feed.json",
+];
+
+///Probes are guesses, most of them fail and aren't worth a retry
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+static BASE_TAG: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"(?is)<base\b[^>]*>"#).expect("valid regex"));
+static LINK_TAG: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"(?is)<link\b[^>]*>"#).expect("valid regex"));
+///Name and double quoted, single quoted or bare value
+static ATTRIBUTE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?is)\s([^\s"'>/=]+)\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'>]+))"#)
+        .expect("valid regex")
+});
+
+pub(super) fn is_html(response: &HttpResponse) -> bool {
+    if let Some(content_type) = response.header("content-type") {
+        return content_type.contains("html");
+    }
+    let start = response.body.trim_start().to_ascii_lowercase();
+    start.starts_with("<!doctype html") || start.starts_with("<html")
+}
+
+///Feeds advertised by an html page, or found at the usual locations of its site
+pub(super) async fn discover(response: &HttpResponse) -> Vec<FeedCandidate> {
+    let base_url = base_url(response);
+
+    let candidates = advertised_feeds(&response.body, &base_url);
+    if !candidates.is_empty() {
+        return candidates;
+    }
+    probe_common_paths(&base_url).await
+}
+
+fn base_url(response: &HttpResponse) -> Option<url::Url> {
+    let page_url = url::Url::parse(&response.final_url).ok()?;
+    let base = BASE_TAG
+        .find(&response.body)
+        .and_then(|tag| attribute(tag.as_str(), "href"))
+        .and_then(|href| page_url.join(&href).ok());
+    Some(base.unwrap_or(page_url))
+}
+
+fn advertised_feeds(html: &str, base_url: &Option<url::Url>) -> Vec<FeedCandidate> {
+    let mut candidates: Vec<FeedCandidate> = Vec::new();
+    for tag in LINK_TAG.find_iter(html).map(|tag| tag.as_str()) {
+        let is_alternate = attribute(tag, "rel")
+            .map(|rel| {
+                rel.split_whitespace()
+                    .any(|rel| rel.eq_ignore_ascii_case("alternate"))
+            })
+            .unwrap_or(false);
+        let feed_type = attribute(tag, "type")
+            .map(|feed_type| feed_type.trim().to_ascii_lowercase())
+            .filter(|feed_type| FEED_TYPES.contains(&feed_type.as_str()));
+        let href = attribute(tag, "href");
+
+        let (true, Some(feed_type), Some(href)) = (is_alternate, feed_type, href) else {
+            continue;
+        };
+        let url = match base_url {
+            Some(base_url) => match base_url.join(href.trim()) {
+                Ok(url) => url.to_string(),
+                Err(_) => continue,
+            },
+            None => href,
+        };
+        if candidates.iter().any(|candidate| candidate.url == url) {
+            continue;
+        }
+        candidates.push(FeedCandidate {
+            url,
+            title: attribute(tag, "title").filter(|title| !title.trim().is_empty()),
+            feed_type: Some(feed_type),
+        });
+    }
+    candidates
+}
+
+///The first of the usual locations, in the order of `COMMON_PATHS`, that serves a feed
+///They are probed all at once, a path wins once the ones before it have failed
+async fn probe_common_paths(base_url: &Option<url::Url>) -> Vec<FeedCandidate> {
+    let Some(base_url) = base_url else {
+        return Vec::new();
+    };
+
+    let mut probes = JoinSet::new();
+    // None while the path is being probed, then whether it serves a feed
+    let mut results: Vec<Option<Option<FeedCandidate>>> = Vec::new();
+    for (index, path) in COMMON_PATHS.iter().enumerate() {
+        match base_url.join(path) {
+            Ok(url) => {
+                probes.spawn(async move { (index, probe(url.to_string()).await) });
+                results.push(None);
+            }
+            Err(_) => results.push(Some(None)),
+        }
+    }
+
+    // Dropping the set aborts the probes still running
+    while let Some(probed) = probes.join_next().await {
+        if let Ok((index, candidate)) = probed {
+            results[index] = Some(candidate);
+        }
+        let settled = results.iter().map_while(Option::as_ref);
+        if let Some(candidate) = settled.flatten().next() {
+            return vec![candidate.clone()];
+        }
+    }
+    // A probe that panicked leaves a gap
+    results.into_iter().flatten().flatten().take(1).collect()
+}
+
+async fn probe(url: Url) -> Option<FeedCandidate> {
+    let response = HttpReqwest {}.fetch_once(&url, PROBE_TIMEOUT).await.ok()?;
+    let feed = Feed::parse(&response.body, response.header("content-type")).ok()?;
+    Some(FeedCandidate {
+        // /feed and /rss often redirect to the document's real location
+        url: response.permanent_url().cloned().unwrap_or(url),
+        title: Some(feed.title).filter(|title| !title.trim().is_empty()),
+        feed_type: response.header("content-type").map(str::to_string),
+    })
+}
+
+///Value of an html attribute, with the common entities decoded
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    let captures = ATTRIBUTE
+        .captures_iter(tag)
+        .find(|captures| captures[1].eq_ignore_ascii_case(name))?;
+    let value = captures
+        .get(2)
+        .or_else(|| captures.get(3))
+        .or_else(|| captures.get(4))?
+        .as_str();
+    Some(
+        value
+            .replace("&quot;", "\"")
+            .replace("&#39;", "'")
+            .replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&amp;", "&"),
+    )
+}
@@ -1,17 +1,24 @@
+use quick_xml::events::{BytesEnd, Event};
+use quick_xml::name::QName;
 use serde::{Deserialize, Serialize};
 
 use crate::error;
+use crate::shared::date::parse_timestamp;
 use crate::shared::errors::*;
 use crate::shared::types::Url;
-use crate::service::http::IHttp;
+use crate::service::http::{HttpResponse, IHttp};
 
+use super::discovery;
 use super::article::entities::Article;
+use super::rule::entities::ArticleNotification;
 
 
 #[derive(Debug, PartialEq)]
 enum FeedType {
     Rss,
     Atom,
+    Json,
+    Rdf,
     Unknown,
 }
 
@@ -22,8 +29,43 @@ pub struct Feed {
     pub(super) xml_url: Url,
     pub(super) link: Option<Url>,
     pub(super) description: Option<String>,
+    pub(super) language: Option<String>,
+    ///Logo or icon
+    pub(super) image_url: Option<Url>,
+    pub(super) generator: Option<String>,
+    ///When the publisher last changed the feed, unix timestamp
+    pub(super) last_build_at: Option<i64>,
     pub(super) articles: Vec<Article>,
-    pub(super) category_id: Option<i32>,
+    pub(super) category_ids: Vec<i32>,
+    #[serde(default)]
+    pub(super) tag_ids: Vec<i32>,
+    pub(super) refresh_interval: Option<i64>,
+    pub(super) refresh_cron: Option<String>,
+    pub(super) last_fetched_at: Option<i64>,
+    pub(super) next_fetch_at: Option<i64>,
+    pub(super) error_count: i64,
+    pub(super) last_error: Option<String>,
+    pub(super) is_dead: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RefreshResult {
+    pub(super) feed_id: i32,
+    pub(super) inserted: usize,
+    pub(super) updated: usize,
+    pub(super) not_modified: bool,
+    pub(super) moved_to: Option<Url>,
+    pub(super) error: Option<String>,
+    ///New articles matched by a rule that notifies
+    pub(super) notifications: Vec<ArticleNotification>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FeedUrlChange {
+    pub(super) old_url: Url,
+    pub(super) new_url: Url,
+    pub(super) status: u16,
+    pub(super) changed_at: i64,
 }
 
 pub(super) struct FeedBuilder {
@@ -32,8 +74,20 @@ pub(super) struct FeedBuilder {
     xml_url: Option<Url>,
     link: Option<Url>,
     description: Option<String>,
+    language: Option<String>,
+    image_url: Option<Url>,
+    generator: Option<String>,
+    last_build_at: Option<i64>,
     articles: Option<Vec<Article>>,
-    category_id: Option<i32>,
+    category_ids: Vec<i32>,
+    tag_ids: Vec<i32>,
+    refresh_interval: Option<i64>,
+    refresh_cron: Option<String>,
+    last_fetched_at: Option<i64>,
+    next_fetch_at: Option<i64>,
+    error_count: Option<i64>,
+    last_error: Option<String>,
+    is_dead: bool,
 }
 
 impl FeedBuilder {
@@ -52,13 +106,33 @@ impl FeedBuilder {
         self
     }
 
-    pub fn _link(mut self, link: Url) -> Self {
-        self.link = Some(link);
+    pub fn link(mut self, link: Option<Url>) -> Self {
+        self.link = link;
+        self
+    }
+
+    pub fn description(mut self, description: Option<String>) -> Self {
+        self.description = description;
+        self
+    }
+
+    pub fn language(mut self, language: Option<String>) -> Self {
+        self.language = language;
         self
     }
 
-    pub fn _description(mut self, description: String) -> Self {
-        self.description = Some(description);
+    pub fn image_url(mut self, image_url: Option<Url>) -> Self {
+        self.image_url = image_url;
+        self
+    }
+
+    pub fn generator(mut self, generator: Option<String>) -> Self {
+        self.generator = generator;
+        self
+    }
+
+    pub fn last_build_at(mut self, last_build_at: Option<i64>) -> Self {
+        self.last_build_at = last_build_at;
         self
     }
 
@@ -67,8 +141,48 @@ impl FeedBuilder {
         self
     }
 
-    pub fn category_id(mut self, category_id: i32) -> Self {
-        self.category_id = Some(category_id);
+    pub fn category_ids(mut self, category_ids: Vec<i32>) -> Self {
+        self.category_ids = category_ids;
+        self
+    }
+
+    pub fn tag_ids(mut self, tag_ids: Vec<i32>) -> Self {
+        self.tag_ids = tag_ids;
+        self
+    }
+
+    pub fn refresh_interval(mut self, refresh_interval: Option<i64>) -> Self {
+        self.refresh_interval = refresh_interval;
+        self
+    }
+
+    pub fn refresh_cron(mut self, refresh_cron: Option<String>) -> Self {
+        self.refresh_cron = refresh_cron;
+        self
+    }
+
+    pub fn last_fetched_at(mut self, last_fetched_at: Option<i64>) -> Self {
+        self.last_fetched_at = last_fetched_at;
+        self
+    }
+
+    pub fn next_fetch_at(mut self, next_fetch_at: Option<i64>) -> Self {
+        self.next_fetch_at = next_fetch_at;
+        self
+    }
+
+    pub fn error_count(mut self, error_count: i64) -> Self {
+        self.error_count = Some(error_count);
+        self
+    }
+
+    pub fn last_error(mut self, last_error: Option<String>) -> Self {
+        self.last_error = last_error;
+        self
+    }
+
+    pub fn dead(mut self, is_dead: bool) -> Self {
+        self.is_dead = is_dead;
         self
     }
 
@@ -79,8 +193,20 @@ impl FeedBuilder {
             xml_url: self.xml_url.unwrap_or("".to_string()),
             link: self.link,
             description: self.description,
+            language: self.language,
+            image_url: self.image_url,
+            generator: self.generator,
+            last_build_at: self.last_build_at,
             articles: self.articles.unwrap_or_default(),
-            category_id: self.category_id,
+            category_ids: self.category_ids,
+            tag_ids: self.tag_ids,
+            refresh_interval: self.refresh_interval,
+            refresh_cron: self.refresh_cron,
+            last_fetched_at: self.last_fetched_at,
+            next_fetch_at: self.next_fetch_at,
+            error_count: self.error_count.unwrap_or(0),
+            last_error: self.last_error,
+            is_dead: self.is_dead,
         }
     }
 }
@@ -93,25 +219,62 @@ impl Feed {
             xml_url: None,
             link: None,
             description: None,
+            language: None,
+            image_url: None,
+            generator: None,
+            last_build_at: None,
             articles: None,
-            category_id: None,
+            category_ids: Vec::new(),
+            tag_ids: Vec::new(),
+            refresh_interval: None,
+            refresh_cron: None,
+            last_fetched_at: None,
+            next_fetch_at: None,
+            error_count: None,
+            last_error: None,
+            is_dead: false,
         }
     }
 
     pub(super) fn from_rss(rss: RssFeed) -> Self {
+        let last_build_date = rss
+            .channel
+            .last_build_date
+            .as_deref()
+            .or(rss.channel.pub_date.as_deref())
+            .or(rss.channel.date.as_deref());
         Self {
             id: None,
             title: rss.channel.title,
             xml_url: String::new(),
-            link: None,        // Rss link handling
-            description: None, //Rss.channel.description,
+            link: rss
+                .channel
+                .link
+                .map(|link| link.trim().to_string())
+                .filter(|link| !link.is_empty()),
+            description: rss
+                .channel
+                .description
+                .filter(|description| !description.trim().is_empty()),
+            language: rss.channel.language,
+            image_url: rss.channel.image.and_then(|image| image.url),
+            generator: rss.channel.generator,
+            last_build_at: last_build_date.and_then(parse_timestamp),
             articles: rss
                 .channel
                 .items
                 .into_iter()
                 .map(Article::from_rss)
                 .collect(),
-            category_id: None,
+            category_ids: Vec::new(),
+            tag_ids: Vec::new(),
+            refresh_interval: None,
+            refresh_cron: None,
+            last_fetched_at: None,
+            next_fetch_at: None,
+            error_count: 0,
+            last_error: None,
+            is_dead: false,
         }
     }
 
@@ -120,55 +283,164 @@ impl Feed {
             id: None,
             title: atom.title,
             xml_url: String::new(),
-            // link: atom.link.map(|l| l.href),
-            link: None,
-            description: None, // Atom description handling
+            // A link without rel is an alternate link
+            link: atom
+                .links
+                .iter()
+                .find(|link| matches!(link.rel.as_deref(), None | Some("alternate")))
+                .and_then(|link| link.href.clone()),
+            description: atom.subtitle,
+            language: atom.lang,
+            image_url: atom.logo.or(atom.icon),
+            generator: atom.generator.and_then(|generator| generator.name),
+            last_build_at: atom.updated.as_deref().and_then(parse_timestamp),
             articles: atom
                 .entries
                 .into_iter()
                 .map(Article::from_atom)
                 .collect(),
-            category_id: None,
+            category_ids: Vec::new(),
+            tag_ids: Vec::new(),
+            refresh_interval: None,
+            refresh_cron: None,
+            last_fetched_at: None,
+            next_fetch_at: None,
+            error_count: 0,
+            last_error: None,
+            is_dead: false,
+        }
+    }
+
+    pub(super) fn from_rdf(rdf: RdfFeed) -> Self {
+        let channel_creator = rdf.channel.creator;
+        Self {
+            id: None,
+            title: rdf.channel.title,
+            xml_url: String::new(),
+            link: rdf.channel.link,
+            description: rdf.channel.description,
+            language: rdf.channel.language,
+            image_url: rdf.image.and_then(|image| image.url),
+            generator: None,
+            last_build_at: rdf.channel.date.as_deref().and_then(parse_timestamp),
+            articles: rdf
+                .items
+                .into_iter()
+                .map(|item| Article::from_rdf(item, channel_creator.as_deref()))
+                .collect(),
+            category_ids: Vec::new(),
+            tag_ids: Vec::new(),
+            refresh_interval: None,
+            refresh_cron: None,
+            last_fetched_at: None,
+            next_fetch_at: None,
+            error_count: 0,
+            last_error: None,
+            is_dead: false,
+        }
+    }
+
+    pub(super) fn from_json(json: JsonFeed) -> Self {
+        Self {
+            id: None,
+            title: json.title,
+            xml_url: String::new(),
+            link: json.home_page_url,
+            description: json.description,
+            language: json.language,
+            image_url: json.icon.or(json.favicon),
+            generator: None,
+            last_build_at: None,
+            articles: json.items.into_iter().map(Article::from_json).collect(),
+            category_ids: Vec::new(),
+            tag_ids: Vec::new(),
+            refresh_interval: None,
+            refresh_cron: None,
+            last_fetched_at: None,
+            next_fetch_at: None,
+            error_count: 0,
+            last_error: None,
+            is_dead: false,
         }
     }
 
-    pub(super) async fn from_url(url: Url) -> Result<Self> {
+    ///The feed with the response it came in, for its redirects and cache validators
+    pub(super) async fn from_url(url: Url) -> Result<(Self, HttpResponse)> {
         let http = crate::service::http::HttpReqwest {};
 
-        let content = http
-            .fetch(&url)
-            .await
-            .map_err(|e| error!(ErrorType::Entity(e.to_string())))?;
+        let response = http.fetch(&url).await?;
 
-        let reader = quick_xml::Reader::from_str(&content);
+        let mut feed = match Self::parse(&response.body, response.header("content-type")) {
+            Ok(feed) => feed,
+            Err(e) if discovery::is_html(&response) => {
+                let candidates = discovery::discover(&response).await;
+                if candidates.is_empty() {
+                    return Err(e);
+                }
+                return Err(error!(ErrorType::NotAFeed(candidates)));
+            }
+            Err(e) => return Err(e),
+        };
+        feed.xml_url = response.permanent_url().unwrap_or(&url).to_owned();
+
+        Ok((feed, response))
+    }
+
+    ///Parse a feed document, xml_url is left empty
+    pub(super) fn parse(content: &str, content_type: Option<&str>) -> Result<Self> {
+        let content = content.trim_start_matches('\u{feff}');
 
-        let mut feed: Self = match Self::determine_feed_type(reader) {
+        let feed: Self = match Self::determine_feed_type(content, content_type) {
             FeedType::Unknown => Err(error!(ErrorType::XmlBadFormat))?,
             FeedType::Rss => {
+                let content = rename_prefixed_elements(content)?;
                 let rss: RssFeed = quick_xml::de::from_str(&content)
                     .map_err(|e| error!(ErrorType::XmlDeserialize(e.to_string())))?;
                 Feed::from_rss(rss)
             }
             FeedType::Atom => {
-                let atom: AtomFeed = quick_xml::de::from_str(&content)
+                let atom: AtomFeed = quick_xml::de::from_str(content)
                     .map_err(|e| error!(ErrorType::XmlDeserialize(e.to_string())))?;
                 Feed::from_atom(atom)
             }
+            FeedType::Rdf => {
+                let rdf: RdfFeed = quick_xml::de::from_str(content)
+                    .map_err(|e| error!(ErrorType::XmlDeserialize(e.to_string())))?;
+                Feed::from_rdf(rdf)
+            }
+            FeedType::Json => {
+                let json: JsonFeed = serde_json::from_str(content)
+                    .map_err(|e| error!(ErrorType::JsonDeserialize(e.to_string())))?;
+                if !json.version.starts_with("https://jsonfeed.org/version/1") {
+                    Err(error!(ErrorType::JsonDeserialize(format!(
+                        "unsupported JSON Feed version {}",
+                        json.version
+                    ))))?
+                }
+                Feed::from_json(json)
+            }
         };
 
-        feed.xml_url = url.to_owned();
-
         Ok(feed)
     }
 
-    fn determine_feed_type(mut reader: quick_xml::Reader<&[u8]>) -> FeedType {
+    fn determine_feed_type(content: &str, content_type: Option<&str>) -> FeedType {
+        let is_json_type = content_type
+            .map(|content_type| content_type.to_ascii_lowercase().contains("json"))
+            .unwrap_or(false);
+        if is_json_type || content.trim_start().starts_with('{') {
+            return FeedType::Json;
+        }
+
+        let mut reader = quick_xml::Reader::from_str(content);
         let mut buf = Vec::new();
         loop {
             match reader.read_event_into(&mut buf) {
                 Ok(quick_xml::events::Event::Start(ref e)) => {
-                    return match e.name().as_ref() {
+                    return match e.local_name().as_ref() {
                         b"rss" => FeedType::Rss,
                         b"feed" => FeedType::Atom,
+                        b"RDF" => FeedType::Rdf,
                         _ => FeedType::Unknown,
                     };
                 }
@@ -185,14 +457,117 @@ impl Feed {
     }
 }
 
+///Namespaced elements the RSS structs read through their local name, like `dc:creator`
+const READ_PREFIXED: [&[u8]; 3] = [b"encoded", b"creator", b"date"];
+
+///quick-xml matches on local names, `<itunes:image>` would be taken for a second `<image>`
+///The other prefixed elements become `itunes-image`, text and CDATA are copied as they are
+fn rename_prefixed_elements(content: &str) -> Result<String> {
+    let mut reader = quick_xml::Reader::from_str(content);
+    let mut writer = quick_xml::Writer::new(Vec::with_capacity(content.len()));
+    loop {
+        let event = match reader.read_event() {
+            Ok(Event::Eof) => break,
+            Ok(Event::Start(mut start)) => {
+                if let Some(name) = unprefixed_name(start.name()) {
+                    start.set_name(name.as_bytes());
+                }
+                Event::Start(start)
+            }
+            Ok(Event::Empty(mut empty)) => {
+                if let Some(name) = unprefixed_name(empty.name()) {
+                    empty.set_name(name.as_bytes());
+                }
+                Event::Empty(empty)
+            }
+            Ok(Event::End(end)) => match unprefixed_name(end.name()) {
+                Some(name) => Event::End(BytesEnd::new(name)),
+                None => Event::End(end),
+            },
+            Ok(event) => event,
+            Err(e) => Err(error!(ErrorType::XmlDeserialize(e.to_string())))?,
+        };
+        writer
+            .write_event(event)
+            .map_err(|e| error!(ErrorType::XmlDeserialize(e.to_string())))?;
+    }
+
+    String::from_utf8(writer.into_inner())
+        .map_err(|e| error!(ErrorType::XmlDeserialize(e.to_string())))
+}
+
+///`itunes:image` becomes `itunes-image`, None for the names kept as they are
+fn unprefixed_name(name: QName) -> Option<String> {
+    let prefix = name.prefix()?;
+    let local_name = name.local_name();
+    if READ_PREFIXED.contains(&local_name.as_ref()) {
+        return None;
+    }
+    Some(format!(
+        "{}-{}",
+        String::from_utf8_lossy(prefix.as_ref()),
+        String::from_utf8_lossy(local_name.as_ref())
+    ))
+}
+
+///https://www.jsonfeed.org/version/1.1/
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub(super) struct JsonFeed {
+    pub version: String,
+    pub title: String,
+    pub home_page_url: Option<String>,
+    pub feed_url: Option<String>,
+    pub description: Option<String>,
+    pub icon: Option<String>,
+    pub favicon: Option<String>,
+    pub language: Option<String>,
+    ///1.0 has a single author, 1.1 deprecated it for authors
+    pub author: Option<JsonAuthor>,
+    pub authors: Option<Vec<JsonAuthor>>,
+    #[serde(default)]
+    pub items: Vec<JsonItem>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub(super) struct JsonItem {
+    ///Must be a string but some publishers use numbers
+    pub id: Option<serde_json::Value>,
+    pub url: Option<String>,
+    pub external_url: Option<String>,
+    pub title: Option<String>,
+    pub content_html: Option<String>,
+    pub content_text: Option<String>,
+    pub summary: Option<String>,
+    pub image: Option<String>,
+    pub date_published: Option<String>,
+    pub date_modified: Option<String>,
+    pub author: Option<JsonAuthor>,
+    pub authors: Option<Vec<JsonAuthor>>,
+    pub tags: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub(super) struct JsonAuthor {
+    pub name: Option<String>,
+    pub url: Option<String>,
+    pub avatar: Option<String>,
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub(super) struct AtomFeed {
     pub title: String,
     pub subtitle: Option<String>,
-    // pub links: Vec<AtomLink>,
+    #[serde(rename = "link", default)]
+    pub links: Vec<AtomLink>,
+    ///xml:lang
+    #[serde(rename = "@lang")]
+    pub lang: Option<String>,
+    pub icon: Option<String>,
+    pub logo: Option<String>,
+    pub generator: Option<AtomGenerator>,
     // pub id: String,
-    // pub updated: String,
+    pub updated: Option<String>,
     // // pub updated: DateTime<FixedOffset>,
     // pub authors: Vec<AtomPerson>,
     #[serde(rename = "entry", default)]
@@ -208,12 +583,12 @@ pub(super) struct AtomEntry {
     pub id: Option<String>,
     pub updated: Option<String>,
     pub published: Option<String>,
-    // pub updated: DateTime<FixedOffset>,
-    // pub published: Option<DateTime<FixedOffset>>,
     #[serde(rename = "author", default)]
     pub authors: Option<Vec<AtomPerson>>,
-    pub content: AtomContent,
+    pub content: Option<AtomContent>,
     pub summary: Option<String>,
+    #[serde(rename = "category", default)]
+    pub categories: Vec<AtomCategory>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -228,6 +603,21 @@ pub(super) struct AtomLink {
     pub title: Option<String>,
 }
 
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub(super) struct AtomCategory {
+    #[serde(rename = "@term")]
+    pub term: Option<String>,
+    ///Human readable version of the term
+    #[serde(rename = "@label")]
+    pub label: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub(super) struct AtomGenerator {
+    #[serde(rename = "$text")]
+    pub name: Option<String>,
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub(super) struct AtomPerson {
     pub name: Option<String>,
@@ -243,7 +633,47 @@ pub(super) struct AtomContent {
     pub content: String,
 }
 
-// use chrono::{DateTime, FixedOffset};
+///RSS 1.0, https://web.resource.org/rss/1.0/spec
+///quick-xml matches on local names, `dc:date` is read as `date`
+#[derive(Debug, Deserialize, Serialize)]
+pub(super) struct RdfFeed {
+    pub channel: RdfChannel,
+    ///Items are siblings of the channel, not children
+    #[serde(rename = "item", default)]
+    pub items: Vec<RdfItem>,
+    ///Sibling of the channel too, the channel only points to it
+    pub image: Option<Image>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub(super) struct RdfChannel {
+    #[serde(rename = "@about")]
+    pub about: Option<String>,
+    pub title: String,
+    pub link: Option<String>,
+    pub description: Option<String>,
+    // dc:
+    pub date: Option<String>,
+    pub creator: Option<String>,
+    pub language: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub(super) struct RdfItem {
+    #[serde(rename = "@about")]
+    pub about: Option<String>,
+    pub title: String,
+    pub link: Option<String>,
+    pub description: Option<String>,
+    ///content:encoded
+    pub encoded: Option<String>,
+    // dc:
+    pub date: Option<String>,
+    pub creator: Option<String>,
+    #[serde(rename = "subject", default)]
+    pub subjects: Vec<String>,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub(super) struct RssFeed {
@@ -254,25 +684,25 @@ pub(super) struct RssFeed {
 #[serde(rename_all = "camelCase")]
 pub(super) struct Channel {
     pub title: String,
-    // pub link: String,
+    pub link: Option<String>,
     pub description: Option<String>,
-    // pub language: Option<String>,
+    pub language: Option<String>,
     // pub copyright: Option<String>,
     // #[serde(rename = "managingEditor")]
     // pub managing_editor: Option<String>,
     // pub webmaster: Option<String>,
-    // #[serde(rename = "pubDate")]
-    // pub pub_date: Option<String>,
-    // // pub pub_date: Option<DateTime<FixedOffset>>,
-    // #[serde(rename = "lastBuildDate")]
-    // pub last_build_date: Option<String>,
-    // // pub last_build_date: Option<DateTime<FixedOffset>>,
+    #[serde(rename = "pubDate")]
+    pub pub_date: Option<String>,
+    #[serde(rename = "lastBuildDate")]
+    pub last_build_date: Option<String>,
+    ///dc:date
+    pub date: Option<String>,
     // pub category: Option<String>,
-    // pub generator: Option<String>,
+    pub generator: Option<String>,
     // pub docs: Option<String>,
     // pub cloud: Option<Cloud>,
     // pub ttl: Option<i32>,
-    // pub image: Option<Image>,
+    pub image: Option<Image>,
     // pub rating: Option<String>,
     // #[serde(rename = "textInput")]
     // pub text_input: Option<TextInput>,
@@ -294,16 +724,16 @@ pub(super) struct Channel {
 //     pub protocol: String,
 // }
 
-// #[derive(Debug, Deserialize, Serialize)]
-// #[serde(rename_all = "camelCase")]
-// pub struct Image {
-//     pub url: String,
-//     pub title: String,
-//     pub link: String,
-//     pub width: Option<i32>,
-//     pub height: Option<i32>,
-//     pub description: Option<String>,
-// }
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct Image {
+    pub url: Option<String>,
+    pub title: Option<String>,
+    pub link: Option<String>,
+    // pub width: Option<i32>,
+    // pub height: Option<i32>,
+    // pub description: Option<String>,
+}
 
 // #[derive(Debug, Deserialize, Serialize)]
 // #[serde(rename_all = "camelCase")]
@@ -319,15 +749,20 @@ pub(super) struct Channel {
 pub(super) struct Item {
     pub title: String,
     pub link: Option<String>,
-    pub description: String,
+    pub description: Option<String>,
+    ///content:encoded
+    pub encoded: Option<String>,
     pub author: Option<String>,
+    ///dc:creator, used by most feeds instead of author which must be an email
+    pub creator: Option<String>,
     pub category: Option<Vec<Category>>,
     pub comments: Option<String>,
     pub enclosure: Option<Enclosure>,
     pub guid: Option<Guid>,
     #[serde(rename = "pubDate")]
     pub pub_date: Option<String>,
-    // pub pub_date: Option<DateTime<FixedOffset>>,
+    ///dc:date
+    pub date: Option<String>,
     pub source: Option<Source>,
 }
 
@@ -364,3 +799,204 @@ pub(super) struct Source {
     #[serde(rename = "$value")]
     pub value: Option<String>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ITUNES_PODCAST: &str = include_str!("../../../tests/fixtures/itunes-podcast.rss");
+
+    #[test]
+    fn parses_podcast_with_itunes_elements() {
+        let feed = Feed::parse(ITUNES_PODCAST, Some("application/rss+xml")).unwrap();
+
+        assert_eq!(feed.title, "Compiler Talk");
+        assert_eq!(feed.link.as_deref(), Some("https://podcast.example.com/"));
+        assert_eq!(feed.language.as_deref(), Some("en-us"));
+        assert_eq!(
+            feed.image_url.as_deref(),
+            Some("https://podcast.example.com/artwork-144.jpg")
+        );
+        assert_eq!(feed.generator.as_deref(), Some("Podcast Host 4.2"));
+        assert_eq!(feed.last_build_at, Some(1727769600));
+        assert_eq!(feed.articles.len(), 2);
+
+        let article = serde_json::to_value(&feed.articles[0]).unwrap();
+        assert_eq!(article["title"], "Episode 42: Borrow checking");
+        assert_eq!(article["guid"], "compiler-talk-42");
+        assert_eq!(article["link"], "https://podcast.example.com/episodes/42");
+        assert_eq!(article["author"], "Ada");
+        assert_eq!(
+            article["content"],
+            "<p>Show notes, see <itunes:image> in the feed.</p>"
+        );
+        assert_eq!(article["categories"], serde_json::json!(["Rust"]));
+    }
+
+    #[test]
+    fn keeps_rss_without_prefixed_elements() {
+        let content = r#"<rss version="2.0"><channel><title>Plain</title><link>https://example.com/</link>
+            <item><title>One</title><link>https://example.com/1</link></item></channel></rss>"#;
+        let feed = Feed::parse(content, None).unwrap();
+
+        assert_eq!(feed.link.as_deref(), Some("https://example.com/"));
+        assert_eq!(feed.articles.len(), 1);
+    }
+
+    #[test]
+    fn parses_atom() {
+        let content = r#"<?xml version="1.0" encoding="utf-8"?>
+            <feed xmlns="http://www.w3.org/2005/Atom" xml:lang="fr">
+                <title>Atom Blog</title>
+                <subtitle>Notes</subtitle>
+                <link rel="self" href="https://example.com/atom.xml"/>
+                <link href="https://example.com/"/>
+                <icon>https://example.com/icon.png</icon>
+                <generator uri="https://gohugo.io/">Hugo</generator>
+                <updated>2024-08-26T10:00:00Z</updated>
+                <entry>
+                    <title>First</title>
+                    <link rel="edit" href="https://example.com/edit/1"/>
+                    <link rel="alternate" href="https://example.com/1"/>
+                    <id>tag:example.com,2024:1</id>
+                    <updated>2024-08-26T10:00:00Z</updated>
+                    <author><name>Grace</name></author>
+                    <category term="rust" label="Rust"/>
+                    <summary>Short</summary>
+                    <content type="html">&lt;p&gt;Long&lt;/p&gt;</content>
+                </entry>
+            </feed>"#;
+        let feed = Feed::parse(content, None).unwrap();
+
+        assert_eq!(feed.title, "Atom Blog");
+        assert_eq!(feed.link.as_deref(), Some("https://example.com/"));
+        assert_eq!(feed.description.as_deref(), Some("Notes"));
+        assert_eq!(feed.language.as_deref(), Some("fr"));
+        assert_eq!(
+            feed.image_url.as_deref(),
+            Some("https://example.com/icon.png")
+        );
+        assert_eq!(feed.last_build_at, Some(1724666400));
+        assert_eq!(feed.articles.len(), 1);
+
+        let article = serde_json::to_value(&feed.articles[0]).unwrap();
+        assert_eq!(article["guid"], "tag:example.com,2024:1");
+        assert_eq!(article["link"], "https://example.com/1");
+        assert_eq!(article["author"], "Grace");
+        assert_eq!(article["content"], "<p>Long</p>");
+        assert_eq!(article["summary"], "Short");
+        // No published date, the updated one stands in
+        assert_eq!(article["published_at"], 1724666400);
+        assert_eq!(article["categories"], serde_json::json!(["Rust"]));
+    }
+
+    #[test]
+    fn parses_rdf() {
+        let content = r#"<?xml version="1.0"?>
+            <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#"
+                xmlns="http://purl.org/rss/1.0/" xmlns:dc="http://purl.org/dc/elements/1.1/">
+                <channel rdf:about="https://example.com/rss">
+                    <title>RDF Site</title>
+                    <link>https://example.com/</link>
+                    <description>Old school</description>
+                    <dc:creator>Site Team</dc:creator>
+                    <dc:date>2024-08-25T08:00:00Z</dc:date>
+                </channel>
+                <image rdf:about="https://example.com/logo.png">
+                    <url>https://example.com/logo.png</url>
+                </image>
+                <item rdf:about="https://example.com/a">
+                    <title>A</title>
+                    <link>https://example.com/a</link>
+                    <description>About A</description>
+                    <dc:date>Sun, 25 Aug 2024 08:00:00 GMT</dc:date>
+                    <dc:subject>News</dc:subject>
+                </item>
+                <item>
+                    <title>B</title>
+                    <link>https://example.com/b</link>
+                    <dc:creator>Linus</dc:creator>
+                </item>
+            </rdf:RDF>"#;
+        let feed = Feed::parse(content, Some("application/rdf+xml")).unwrap();
+
+        assert_eq!(feed.title, "RDF Site");
+        assert_eq!(feed.link.as_deref(), Some("https://example.com/"));
+        assert_eq!(
+            feed.image_url.as_deref(),
+            Some("https://example.com/logo.png")
+        );
+        assert_eq!(feed.last_build_at, Some(1724572800));
+        assert_eq!(feed.articles.len(), 2);
+
+        let first = serde_json::to_value(&feed.articles[0]).unwrap();
+        assert_eq!(first["guid"], "https://example.com/a");
+        assert_eq!(first["content"], "About A");
+        assert_eq!(first["published_at"], 1724572800);
+        // The channel creator stands in for items without one
+        assert_eq!(first["author"], "Site Team");
+        assert_eq!(first["categories"], serde_json::json!(["News"]));
+
+        let second = serde_json::to_value(&feed.articles[1]).unwrap();
+        assert_eq!(second["guid"], "https://example.com/b");
+        assert_eq!(second["author"], "Linus");
+    }
+
+    #[test]
+    fn parses_json_feed() {
+        let content = r#"{
+            "version": "https://jsonfeed.org/version/1.1",
+            "title": "JSON Blog",
+            "home_page_url": "https://example.com/",
+            "favicon": "https://example.com/favicon.ico",
+            "language": "de",
+            "items": [
+                {
+                    "id": 7,
+                    "url": "https://example.com/7",
+                    "content_html": "<p>Hello</p>",
+                    "date_published": "2024-08-27T12:30:00+00:00",
+                    "authors": [{ "name": "Ken" }],
+                    "tags": ["Go"]
+                },
+                {
+                    "id": "note-8",
+                    "title": " ",
+                    "content_text": "A short note without a title"
+                }
+            ]
+        }"#;
+        let feed = Feed::parse(content, Some("application/feed+json")).unwrap();
+
+        assert_eq!(feed.title, "JSON Blog");
+        assert_eq!(feed.link.as_deref(), Some("https://example.com/"));
+        assert_eq!(feed.language.as_deref(), Some("de"));
+        assert_eq!(
+            feed.image_url.as_deref(),
+            Some("https://example.com/favicon.ico")
+        );
+        assert_eq!(feed.articles.len(), 2);
+
+        let first = serde_json::to_value(&feed.articles[0]).unwrap();
+        assert_eq!(first["guid"], "7");
+        assert_eq!(first["content"], "<p>Hello</p>");
+        assert_eq!(first["author"], "Ken");
+        assert_eq!(first["published_at"], 1724761800);
+        assert_eq!(first["categories"], serde_json::json!(["Go"]));
+
+        // Untitled items are titled with their content
+        let second = serde_json::to_value(&feed.articles[1]).unwrap();
+        assert_eq!(second["guid"], "note-8");
+        assert_eq!(second["title"], "A short note without a title");
+    }
+
+    #[test]
+    fn rejects_unknown_documents() {
+        let error = Feed::parse("<html><body>Not a feed</body></html>", None).unwrap_err();
+        assert!(matches!(error.kind(), ErrorType::XmlBadFormat));
+
+        let error =
+            Feed::parse(r#"{"version": "2", "title": "Next", "items": []}"#, None).unwrap_err();
+        assert!(matches!(error.kind(), ErrorType::JsonDeserialize(_)));
+    }
+}
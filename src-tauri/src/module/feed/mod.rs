@@ -2,7 +2,13 @@ pub(super) mod controller;
 pub(super) mod model;
 // pub(super) mod business;
 pub(super) mod entities;
+pub(super) mod discovery;
 pub mod api;
+pub mod scheduler;
 
 pub mod category;
-pub mod article;
\ No newline at end of file
+pub mod article;
+pub mod opml;
+pub mod saved_search;
+pub mod rule;
+pub mod tag;
\ No newline at end of file
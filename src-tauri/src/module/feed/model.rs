@@ -1,82 +1,497 @@
 use super::entities::Feed;
 
 use crate::error;
+use crate::service::http::CacheValidators;
 use crate::shared::errors::*;
-use crate::shared::database::{Db, IDb};
+use rusqlite::{Connection, OptionalExtension};
 
-pub(super) struct FeedModel {
-    db: Db,
+pub(super) struct FeedModel<'a> {
+    connection: &'a Connection,
 }
 
-type DbFeeds = Vec<(i32, String, String, Option<i32>)>;
+#[derive(Clone)]
+pub(super) struct FeedRow {
+    pub id: i32,
+    pub title: String,
+    pub xml_url: String,
+    pub category_ids: Vec<i32>,
+    pub refresh_interval: Option<i64>,
+    pub refresh_cron: Option<String>,
+    pub last_fetched_at: Option<i64>,
+    pub next_fetch_at: Option<i64>,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub error_count: i64,
+    pub last_error: Option<String>,
+    pub is_dead: bool,
+    pub link: Option<String>,
+    pub description: Option<String>,
+    pub language: Option<String>,
+    pub image_url: Option<String>,
+    pub generator: Option<String>,
+    pub last_build_at: Option<i64>,
+    pub tag_ids: Vec<i32>,
+}
 
-impl FeedModel {
-    pub fn new() -> Self {
-        FeedModel { db: Db::new() }
-    }
+const FEED_COLUMNS: &str = "feed.id, feed.title, feed.xml_url, (SELECT GROUP_CONCAT(category_id) FROM feed_category_xref WHERE feed_id = feed.id), feed.refresh_interval, feed.refresh_cron, feed.last_fetched_at, feed.next_fetch_at, feed.etag, feed.last_modified, feed.error_count, feed.last_error, feed.is_dead, feed.link, feed.description, feed.language, feed.image_url, feed.generator, feed.last_build_at,
+    (SELECT GROUP_CONCAT(tag_id) FROM feed_tag_xref WHERE feed_id = feed.id)";
 
-    pub fn open(mut self) -> Result<Self> {
-        self.db.open()?;
-        Ok(self)
-    }
+///Refresh interval in minutes used by feeds without their own
+pub(super) const DEFAULT_REFRESH_INTERVAL: i64 = 30;
 
-    pub fn close(mut self) -> Result<()> {
-        self.db.close()
+impl<'a> FeedModel<'a> {
+    pub fn new(connection: &'a Connection) -> Self {
+        FeedModel { connection }
     }
 
     pub fn insert_feed(&self, feed: &Feed) -> Result<i64> {
-        let connection = self
-            .db
-            .connection
-            .as_ref()
-            .ok_or(error!(ErrorType::Model("DB NOT OPEN".to_string())))?;
+        let connection = self.connection;
 
         connection
             .execute(
-                "INSERT INTO feed (title, xml_url) VALUES (?1, ?2)",
-                (&feed.title, &feed.xml_url),
+                "INSERT INTO feed (title, xml_url, link, description, language, image_url, generator, last_build_at)
+                    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                (
+                    &feed.title,
+                    &feed.xml_url,
+                    &feed.link,
+                    &feed.description,
+                    &feed.language,
+                    &feed.image_url,
+                    &feed.generator,
+                    feed.last_build_at,
+                ),
             )
             .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
         let feed_id = connection.last_insert_rowid();
 
-        if feed.category_id.is_some() {
-            connection
-                .execute(
-                    "INSERT INTO feed_category_xref (feed_id, category_id) VALUES (?1, ?2)",
-                    (feed_id, feed.category_id),
-                )
-                .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
-        }
+        self.set_categories(feed_id as i32, &feed.category_ids)?;
         Ok(feed_id)
     }
 
-    pub fn get_feeds(&self) -> Result<DbFeeds> {
-        let connection = self
-            .db
-            .connection
-            .as_ref()
-            .ok_or(error!(ErrorType::Model("DB NOT OPEN".to_string())))?;
+    fn row_to_feed(row: &rusqlite::Row) -> rusqlite::Result<FeedRow> {
+        Ok(FeedRow {
+            id: row.get(0)?,
+            title: row.get(1)?,
+            xml_url: row.get(2)?,
+            category_ids: row
+                .get::<_, Option<String>>(3)?
+                .map(|ids| ids.split(',').filter_map(|id| id.parse().ok()).collect())
+                .unwrap_or_default(),
+            refresh_interval: row.get(4)?,
+            refresh_cron: row.get(5)?,
+            last_fetched_at: row.get(6)?,
+            next_fetch_at: row.get(7)?,
+            etag: row.get(8)?,
+            last_modified: row.get(9)?,
+            error_count: row.get(10)?,
+            last_error: row.get(11)?,
+            is_dead: row.get(12)?,
+            link: row.get(13)?,
+            description: row.get(14)?,
+            language: row.get(15)?,
+            image_url: row.get(16)?,
+            generator: row.get(17)?,
+            last_build_at: row.get(18)?,
+            tag_ids: row
+                .get::<_, Option<String>>(19)?
+                .map(|ids| ids.split(',').filter_map(|id| id.parse().ok()).collect())
+                .unwrap_or_default(),
+        })
+    }
+
+    pub fn get_feed(&self, feed_id: i32) -> Result<FeedRow> {
+        let connection = self.connection;
+
+        connection
+            .query_row(
+                &format!("SELECT {FEED_COLUMNS} FROM feed WHERE feed.id = ?1"),
+                [feed_id],
+                Self::row_to_feed,
+            )
+            .map_err(|e| match e {
+                rusqlite::Error::QueryReturnedNoRows => error!(ErrorType::NotFound(format!("feed {feed_id}"))),
+                e => error!(ErrorType::Model(e.to_string())),
+            })
+    }
+
+    pub fn get_feed_ids(&self) -> Result<Vec<i32>> {
+        let connection = self.connection;
 
         let mut statement = connection
-            .prepare("SELECT feed.*, xref.category_id FROM feed LEFT JOIN feed_category_xref xref on xref.feed_id = feed.id;")
+            .prepare_cached("SELECT id FROM feed WHERE is_dead = 0 ORDER BY id;")
             .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
 
         let rows = statement
-            .query_map([], |row| {
+            .query_map([], |row| row.get::<_, i32>(0))
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+
+        let mut feed_ids = Vec::new();
+        for row in rows {
+            feed_ids.push(row.map_err(|e| error!(ErrorType::Model(e.to_string())))?);
+        }
+        Ok(feed_ids)
+    }
+
+    ///Feeds that were never fetched or whose next fetch is past due
+    pub fn get_due_feed_ids(&self, now: i64) -> Result<Vec<i32>> {
+        let connection = self.connection;
+
+        let mut statement = connection
+            .prepare_cached("SELECT id FROM feed WHERE is_dead = 0 AND (next_fetch_at IS NULL OR next_fetch_at <= ?1) ORDER BY next_fetch_at;")
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+
+        let rows = statement
+            .query_map([now], |row| row.get::<_, i32>(0))
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+
+        let mut feed_ids = Vec::new();
+        for row in rows {
+            feed_ids.push(row.map_err(|e| error!(ErrorType::Model(e.to_string())))?);
+        }
+        Ok(feed_ids)
+    }
+
+    pub fn update_fetch_times(&self, feed_id: i32, last_fetched_at: i64, next_fetch_at: i64) -> Result<()> {
+        let connection = self.connection;
+
+        connection
+            .execute(
+                "UPDATE feed SET last_fetched_at = ?1, next_fetch_at = ?2 WHERE id = ?3",
+                (last_fetched_at, next_fetch_at, feed_id),
+            )
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        Ok(())
+    }
+
+    ///Consecutive failures drive the backoff, a success resets them
+    pub fn update_error(&self, feed_id: i32, error_count: i64, last_error: Option<String>) -> Result<()> {
+        let connection = self.connection;
+
+        connection
+            .execute(
+                "UPDATE feed SET error_count = ?1, last_error = ?2 WHERE id = ?3",
+                (error_count, last_error, feed_id),
+            )
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        Ok(())
+    }
+
+    ///A dead feed (410 Gone) is left out of the scheduled refreshes
+    pub fn update_dead(&self, feed_id: i32, is_dead: bool) -> Result<()> {
+        let connection = self.connection;
+
+        connection
+            .execute("UPDATE feed SET is_dead = ?1 WHERE id = ?2", (is_dead, feed_id))
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        Ok(())
+    }
+
+    ///Point the feed to its new location and keep a trace of the move
+    pub fn update_xml_url(
+        &self,
+        feed_id: i32,
+        old_url: &str,
+        new_url: &str,
+        status: u16,
+        changed_at: i64,
+    ) -> Result<()> {
+        let connection = self.connection;
+
+        connection
+            .execute(
+                "UPDATE feed SET xml_url = ?1 WHERE id = ?2",
+                (new_url, feed_id),
+            )
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        connection
+            .execute(
+                "INSERT INTO feed_url_history (feed_id, old_url, new_url, status, changed_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+                (feed_id, old_url, new_url, status, changed_at),
+            )
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        Ok(())
+    }
+
+    pub fn get_url_history(&self, feed_id: i32) -> Result<Vec<(String, String, u16, i64)>> {
+        let connection = self.connection;
+
+        let mut statement = connection
+            .prepare_cached("SELECT old_url, new_url, status, changed_at FROM feed_url_history WHERE feed_id = ?1 ORDER BY changed_at DESC, id DESC;")
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+
+        let rows = statement
+            .query_map([feed_id], |row| {
                 Ok((
-                    row.get::<_, i32>(0)?,
+                    row.get::<_, String>(0)?,
                     row.get::<_, String>(1)?,
-                    row.get::<_, String>(2)?,
-                    row.get::<_, Option<i32>>(3)?,
+                    row.get::<_, u16>(2)?,
+                    row.get::<_, i64>(3)?,
                 ))
             })
             .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
 
-        // let feeds: (id, title, xml_url) = rows.collect()?;
-        let mut feeds: DbFeeds = Vec::new();
+        let mut changes = Vec::new();
+        for row in rows {
+            changes.push(row.map_err(|e| error!(ErrorType::Model(e.to_string())))?);
+        }
+        Ok(changes)
+    }
+
+    ///What the publisher says about the feed, a field the document left out keeps its value
+    pub fn update_metadata(&self, feed_id: i32, feed: &Feed) -> Result<()> {
+        let connection = self.connection;
+
+        connection
+            .execute(
+                "UPDATE feed SET link = COALESCE(?1, link), description = COALESCE(?2, description),
+                    language = COALESCE(?3, language), image_url = COALESCE(?4, image_url),
+                    generator = COALESCE(?5, generator), last_build_at = COALESCE(?6, last_build_at)
+                    WHERE id = ?7",
+                (
+                    &feed.link,
+                    &feed.description,
+                    &feed.language,
+                    &feed.image_url,
+                    &feed.generator,
+                    feed.last_build_at,
+                    feed_id,
+                ),
+            )
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        Ok(())
+    }
+
+    pub fn update_validators(&self, feed_id: i32, validators: &CacheValidators) -> Result<()> {
+        let connection = self.connection;
+
+        connection
+            .execute(
+                "UPDATE feed SET etag = ?1, last_modified = ?2 WHERE id = ?3",
+                (&validators.etag, &validators.last_modified, feed_id),
+            )
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        Ok(())
+    }
+
+    pub fn update_schedule(
+        &self,
+        feed_id: i32,
+        refresh_interval: Option<i64>,
+        refresh_cron: Option<String>,
+        next_fetch_at: i64,
+    ) -> Result<()> {
+        let connection = self.connection;
+
+        connection
+            .execute(
+                "UPDATE feed SET refresh_interval = ?1, refresh_cron = ?2, next_fetch_at = ?3 WHERE id = ?4",
+                (refresh_interval, refresh_cron, next_fetch_at, feed_id),
+            )
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        Ok(())
+    }
+
+    pub fn get_default_refresh_interval(&self) -> Result<i64> {
+        let connection = self.connection;
+
+        let value = connection
+            .query_row(
+                "SELECT value FROM setting WHERE key = 'refresh_interval'",
+                [],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+
+        Ok(value
+            .and_then(|value| value.parse::<i64>().ok())
+            .unwrap_or(DEFAULT_REFRESH_INTERVAL))
+    }
+
+    pub fn set_default_refresh_interval(&self, minutes: i64) -> Result<()> {
+        let connection = self.connection;
+
+        connection
+            .execute(
+                "INSERT INTO setting (key, value) VALUES ('refresh_interval', ?1)
+                    ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+                [minutes.to_string()],
+            )
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        Ok(())
+    }
+
+    pub fn get_feeds(&self) -> Result<Vec<FeedRow>> {
+        let connection = self.connection;
+
+        let mut statement = connection
+            .prepare_cached(&format!("SELECT {FEED_COLUMNS} FROM feed ORDER BY feed.id;"))
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+
+        let rows = statement
+            .query_map([], Self::row_to_feed)
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+
+        let mut feeds = Vec::new();
         for row in rows {
             feeds.push(row.map_err(|e| error!(ErrorType::Model(e.to_string())))?);
         }
         Ok(feeds)
     }
+
+    pub fn update_title(&self, feed_id: i32, title: &str) -> Result<()> {
+        let connection = self.connection;
+
+        let updated = connection
+            .execute("UPDATE feed SET title = ?1 WHERE id = ?2", (title, feed_id))
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        if updated == 0 {
+            return Err(error!(ErrorType::NotFound(format!("feed {feed_id}"))));
+        }
+        Ok(())
+    }
+
+    ///A new address is a new feed for the server: forget the validators and errors and fetch it on the next tick
+    pub fn update_url(&self, feed_id: i32, xml_url: &str) -> Result<()> {
+        let connection = self.connection;
+
+        let updated = connection
+            .execute(
+                "UPDATE feed SET xml_url = ?1, etag = NULL, last_modified = NULL, error_count = 0, last_error = NULL,
+                    is_dead = 0, next_fetch_at = NULL
+                    WHERE id = ?2",
+                (xml_url, feed_id),
+            )
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        if updated == 0 {
+            return Err(error!(ErrorType::NotFound(format!("feed {feed_id}"))));
+        }
+        Ok(())
+    }
+
+    ///Replace the categories of the feed
+    pub fn set_categories(&self, feed_id: i32, category_ids: &[i32]) -> Result<()> {
+        let connection = self.connection;
+
+        connection
+            .prepare_cached("DELETE FROM feed_category_xref WHERE feed_id = ?1")
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?
+            .execute([feed_id])
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        for category_id in category_ids {
+            self.add_category(feed_id, *category_id)?;
+        }
+        Ok(())
+    }
+
+    pub fn add_category(&self, feed_id: i32, category_id: i32) -> Result<()> {
+        let connection = self.connection;
+
+        // Selecting from category rejects ids that don't exist, NOT EXISTS keeps the link unique
+        let inserted = connection
+            .prepare_cached(
+                "INSERT INTO feed_category_xref (feed_id, category_id)
+                    SELECT ?1, category.id FROM category
+                    WHERE category.id = ?2
+                        AND NOT EXISTS (SELECT 1 FROM feed_category_xref WHERE feed_id = ?1 AND category_id = ?2)",
+            )
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?
+            .execute((feed_id, category_id))
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        if inserted == 0 && !self.has_category(feed_id, category_id)? {
+            return Err(error!(ErrorType::NotFound(format!("category {category_id}"))));
+        }
+        Ok(())
+    }
+
+    fn has_category(&self, feed_id: i32, category_id: i32) -> Result<bool> {
+        let connection = self.connection;
+
+        connection
+            .prepare_cached("SELECT COUNT(*) FROM feed_category_xref WHERE feed_id = ?1 AND category_id = ?2")
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?
+            .query_row((feed_id, category_id), |row| row.get::<_, i64>(0))
+            .map(|count| count > 0)
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))
+    }
+
+    pub fn remove_category(&self, feed_id: i32, category_id: i32) -> Result<()> {
+        let connection = self.connection;
+
+        connection
+            .prepare_cached("DELETE FROM feed_category_xref WHERE feed_id = ?1 AND category_id = ?2")
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?
+            .execute((feed_id, category_id))
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        Ok(())
+    }
+
+    ///Delete the feed with everything hanging from it, articles included unless another feed has them too
+    ///Returns the number of articles deleted. Meant to run on a transaction
+    pub fn delete_feed(&self, feed_id: i32) -> Result<usize> {
+        let connection = self.connection;
+
+        // Collected before the links to the feed are gone
+        let mut statement = connection
+            .prepare_cached(
+                "SELECT article_id FROM feed_article_xref
+                    WHERE feed_id = ?1
+                        AND article_id NOT IN (SELECT article_id FROM feed_article_xref WHERE feed_id != ?1)",
+            )
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        let rows = statement
+            .query_map([feed_id], |row| row.get::<_, i64>(0))
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        let mut orphan_ids = Vec::new();
+        for row in rows {
+            orphan_ids.push(row.map_err(|e| error!(ErrorType::Model(e.to_string())))?);
+        }
+
+        for query in [
+            "DELETE FROM feed_article_xref WHERE feed_id = ?1",
+            "DELETE FROM feed_category_xref WHERE feed_id = ?1",
+            "DELETE FROM feed_tag_xref WHERE feed_id = ?1",
+            "DELETE FROM feed_author_xref WHERE feed_id = ?1",
+            "DELETE FROM feed_url_history WHERE feed_id = ?1",
+        ] {
+            connection
+                .execute(query, [feed_id])
+                .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        }
+        let deleted = connection
+            .execute("DELETE FROM feed WHERE id = ?1", [feed_id])
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        if deleted == 0 {
+            return Err(error!(ErrorType::NotFound(format!("feed {feed_id}"))));
+        }
+
+        let mut author_statement = connection
+            .prepare_cached("DELETE FROM article_author_xref WHERE article_id = ?1")
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        let mut category_statement = connection
+            .prepare_cached("DELETE FROM article_category WHERE article_id = ?1")
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        let mut tag_statement = connection
+            .prepare_cached("DELETE FROM article_tag_xref WHERE article_id = ?1")
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        let mut article_statement = connection
+            .prepare_cached("DELETE FROM article WHERE id = ?1")
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        for article_id in &orphan_ids {
+            author_statement
+                .execute([article_id])
+                .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+            category_statement
+                .execute([article_id])
+                .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+            tag_statement
+                .execute([article_id])
+                .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+            article_statement
+                .execute([article_id])
+                .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        }
+        Ok(orphan_ids.len())
+    }
 }
@@ -0,0 +1,21 @@
+use crate::shared::database::Db;
+use crate::shared::errors::*;
+
+use super::controller::OpmlController;
+use super::entities::OpmlImportReport;
+
+use tauri::State;
+
+///Subscribe to the feeds of an OPML file, folders become categories
+#[tauri::command]
+pub async fn import_opml(db: State<'_, Db>, path: String) -> Result<OpmlImportReport> {
+    let report = OpmlController::new(db.inner().clone()).import(path).await?;
+    Ok(report)
+}
+
+///Returns the number of feeds written
+#[tauri::command]
+pub async fn export_opml(db: State<'_, Db>, path: String) -> Result<usize> {
+    let feed_count = OpmlController::new(db.inner().clone()).export(path).await?;
+    Ok(feed_count)
+}
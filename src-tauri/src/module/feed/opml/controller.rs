@@ -0,0 +1,200 @@
+use std::collections::HashMap;
+
+use chrono::Local;
+use rusqlite::Connection;
+
+use crate::error;
+use crate::shared::database::Db;
+use crate::shared::errors::*;
+
+use super::super::category::model::{CategoryModel, CategoryTreeRow};
+use super::super::entities::Feed;
+use super::super::model::{FeedModel, FeedRow};
+use super::entities::{Opml, OpmlImportItem, OpmlImportReport, Outline};
+
+pub(super) struct OpmlController {
+    db: Db,
+}
+
+impl OpmlController {
+    pub fn new(db: Db) -> Self {
+        OpmlController { db }
+    }
+
+    ///The feeds are saved as they are in the file, the scheduler fetches them on its next tick
+    pub async fn import(&self, path: String) -> Result<OpmlImportReport> {
+        // File access blocks, it runs on the blocking thread pool like the queries
+        let content = tauri::async_runtime::spawn_blocking(move || {
+            std::fs::read_to_string(&path).map_err(|e| error!(ErrorType::File(path, e.to_string())))
+        })
+        .await
+        .map_err(|e| error!(ErrorType::Custom(e.to_string())))??;
+        let opml: Opml = quick_xml::de::from_str(content.trim_start_matches('\u{feff}'))
+            .map_err(|e| error!(ErrorType::XmlDeserialize(e.to_string())))?;
+
+        self.db
+            .transaction(move |transaction| {
+                // Feeds already subscribed to are never touched, None marks them
+                let mut known: HashMap<String, Option<i32>> = HashMap::new();
+                for feed_row in FeedModel::new(transaction).get_feeds()? {
+                    if let Some(normalized) = normalize_url(&feed_row.xml_url) {
+                        known.insert(normalized, None);
+                    }
+                }
+                let categories_before = CategoryModel::new(transaction).get_categories()?.len();
+
+                let mut report = OpmlImportReport::default();
+                import_outlines(transaction, &opml.body.outlines, None, &mut known, &mut report)?;
+                report.categories_added =
+                    CategoryModel::new(transaction).get_categories()?.len() - categories_before;
+                Ok(report)
+            })
+            .await
+    }
+
+    ///Write every feed under its categories, returns the number of feeds
+    pub async fn export(&self, path: String) -> Result<usize> {
+        let (categories, feeds) = self
+            .db
+            .call(|connection| {
+                Ok((
+                    CategoryModel::new(connection).get_category_tree()?,
+                    FeedModel::new(connection).get_feeds()?,
+                ))
+            })
+            .await?;
+        let feed_count = feeds.len();
+
+        let mut body = String::new();
+        write_outlines(&mut body, None, 2, &categories, &feeds);
+
+        let content = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<opml version=\"2.0\">
+  <head>
+    <title>RSS Reader subscriptions</title>
+    <dateCreated>{}</dateCreated>
+  </head>
+  <body>
+{body}  </body>
+</opml>
+",
+            Local::now().to_rfc2822()
+        );
+        tauri::async_runtime::spawn_blocking(move || {
+            std::fs::write(&path, content).map_err(|e| error!(ErrorType::File(path, e.to_string())))
+        })
+        .await
+        .map_err(|e| error!(ErrorType::Custom(e.to_string())))??;
+        Ok(feed_count)
+    }
+}
+
+fn import_outlines(
+    connection: &Connection,
+    outlines: &[Outline],
+    category_id: Option<i32>,
+    known: &mut HashMap<String, Option<i32>>,
+    report: &mut OpmlImportReport,
+) -> Result<()> {
+    let feed_model = FeedModel::new(connection);
+
+    for outline in outlines {
+        let xml_url = outline.xml_url.as_deref().map(str::trim).unwrap_or("");
+        if xml_url.is_empty() {
+            let title = outline.name().unwrap_or("Untitled");
+            let child_id = CategoryModel::new(connection).get_or_insert_category(title, category_id)?;
+            import_outlines(connection, &outline.outlines, Some(child_id), known, report)?;
+            continue;
+        }
+
+        let title = outline.name().unwrap_or(xml_url).to_string();
+        let item = |reason: Option<&str>| OpmlImportItem {
+            title: title.clone(),
+            xml_url: xml_url.to_string(),
+            reason: reason.map(str::to_string),
+        };
+        let Some(normalized) = normalize_url(xml_url) else {
+            report.failed.push(item(Some("not a valid http(s) url")));
+            continue;
+        };
+
+        match known.get(&normalized) {
+            // Listed in several folders of the file: keep one feed in all of them
+            Some(Some(feed_id)) => {
+                if let Some(category_id) = category_id {
+                    feed_model.add_category(*feed_id, category_id)?;
+                }
+                report.skipped.push(item(Some("listed twice in the file")));
+            }
+            Some(None) => report.skipped.push(item(Some("already subscribed"))),
+            None => {
+                let feed = Feed::builder()
+                    .title(title.clone())
+                    .xml_url(xml_url.to_string())
+                    .link(outline.html_url.clone().filter(|html_url| !html_url.trim().is_empty()))
+                    .category_ids(category_id.into_iter().collect())
+                    .build();
+                let feed_id = feed_model.insert_feed(&feed)? as i32;
+                known.insert(normalized, Some(feed_id));
+                report.added.push(item(None));
+            }
+        }
+    }
+    Ok(())
+}
+
+///Key telling two addresses of the same feed apart from two feeds:
+///scheme, default port, trailing slash and fragment don't count
+fn normalize_url(value: &str) -> Option<String> {
+    let url = url::Url::parse(value.trim()).ok()?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return None;
+    }
+    let host = url.host_str()?;
+    let port = url.port().map(|port| format!(":{port}")).unwrap_or_default();
+    let query = url.query().map(|query| format!("?{query}")).unwrap_or_default();
+    Some(format!(
+        "{host}{port}{}{query}",
+        url.path().trim_end_matches('/')
+    ))
+}
+
+fn write_outlines(
+    out: &mut String,
+    category_id: Option<i32>,
+    depth: usize,
+    categories: &[CategoryTreeRow],
+    feeds: &[FeedRow],
+) {
+    let indent = "  ".repeat(depth);
+
+    for category in categories.iter().filter(|category| {
+        // Categories whose parent is gone are at depth 0 and written at the top
+        match category_id {
+            Some(category_id) => category.1 == Some(category_id) && category.3 > 0,
+            None => category.3 == 0,
+        }
+    }) {
+        let title = quick_xml::escape::escape(category.2.as_str());
+        out.push_str(&format!("{indent}<outline text=\"{title}\" title=\"{title}\">\n"));
+        write_outlines(out, Some(category.0), depth + 1, categories, feeds);
+        out.push_str(&format!("{indent}</outline>\n"));
+    }
+
+    for feed in feeds.iter().filter(|feed| match category_id {
+        Some(category_id) => feed.category_ids.contains(&category_id),
+        None => feed.category_ids.is_empty(),
+    }) {
+        let title = quick_xml::escape::escape(feed.title.as_str());
+        let html_url = feed
+            .link
+            .as_deref()
+            .map(|link| format!(" htmlUrl=\"{}\"", quick_xml::escape::escape(link)))
+            .unwrap_or_default();
+        out.push_str(&format!(
+            "{indent}<outline type=\"rss\" text=\"{title}\" title=\"{title}\" xmlUrl=\"{}\"{html_url}/>\n",
+            quick_xml::escape::escape(feed.xml_url.as_str())
+        ));
+    }
+}
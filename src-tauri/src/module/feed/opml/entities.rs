@@ -0,0 +1,56 @@
+use serde::{Deserialize, Serialize};
+
+use crate::shared::types::Url;
+
+#[derive(Debug, Deserialize)]
+pub(super) struct Opml {
+    pub body: OpmlBody,
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct OpmlBody {
+    #[serde(rename = "outline", default)]
+    pub outlines: Vec<Outline>,
+}
+
+///A feed when it has an xmlUrl, a category otherwise
+#[derive(Debug, Deserialize)]
+pub(super) struct Outline {
+    #[serde(rename = "@text")]
+    pub text: Option<String>,
+    #[serde(rename = "@title")]
+    pub title: Option<String>,
+    #[serde(rename = "@xmlUrl", alias = "@xmlurl")]
+    pub xml_url: Option<Url>,
+    #[serde(rename = "@htmlUrl", alias = "@htmlurl")]
+    pub html_url: Option<Url>,
+    #[serde(rename = "outline", default)]
+    pub outlines: Vec<Outline>,
+}
+
+impl Outline {
+    ///Readers disagree on which one they fill, title is the more specific
+    pub(super) fn name(&self) -> Option<&str> {
+        self.title
+            .as_deref()
+            .or(self.text.as_deref())
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct OpmlImportItem {
+    pub(super) title: String,
+    pub(super) xml_url: Url,
+    ///Why it was skipped or failed
+    pub(super) reason: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct OpmlImportReport {
+    pub(super) added: Vec<OpmlImportItem>,
+    pub(super) skipped: Vec<OpmlImportItem>,
+    pub(super) failed: Vec<OpmlImportItem>,
+    pub(super) categories_added: usize,
+}
@@ -0,0 +1,3 @@
+pub(super) mod controller;
+pub(super) mod entities;
+pub mod api;
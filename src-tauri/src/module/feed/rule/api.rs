@@ -0,0 +1,51 @@
+use crate::shared::database::Db;
+use crate::shared::errors::*;
+
+use super::controller::RuleController;
+use super::entities::{Rule, RuleAction, RuleChanges, RuleConditions, RuleDryRun};
+
+use tauri::State;
+
+///Rules run on the articles a refresh inserts, in order, until one that stops processing matches
+#[tauri::command]
+pub async fn add_rule(
+    db: State<'_, Db>,
+    title: String,
+    conditions: RuleConditions,
+    actions: Vec<RuleAction>,
+    stop_processing: Option<bool>,
+) -> Result<()> {
+    RuleController::new(db.inner().clone())
+        .add(title, conditions, actions, stop_processing.unwrap_or(false))
+        .await?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_all_rules(db: State<'_, Db>) -> Result<Vec<Rule>> {
+    let rules = RuleController::new(db.inner().clone()).get_all().await?;
+    Ok(rules)
+}
+
+#[tauri::command]
+pub async fn update_rule(db: State<'_, Db>, rule_id: i32, changes: RuleChanges) -> Result<()> {
+    RuleController::new(db.inner().clone()).update(rule_id, changes).await?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn delete_rule(db: State<'_, Db>, rule_id: i32) -> Result<()> {
+    RuleController::new(db.inner().clone()).delete(rule_id).await?;
+    Ok(())
+}
+
+///Which existing articles the conditions would match, to try a rule before saving it
+#[tauri::command]
+pub async fn dry_run_rule(
+    db: State<'_, Db>,
+    conditions: RuleConditions,
+    limit: Option<u32>,
+) -> Result<RuleDryRun> {
+    let dry_run = RuleController::new(db.inner().clone()).dry_run(conditions, limit).await?;
+    Ok(dry_run)
+}
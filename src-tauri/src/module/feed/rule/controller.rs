@@ -0,0 +1,147 @@
+use chrono::Local;
+use rusqlite::Connection;
+
+use crate::error;
+use crate::shared::database::Db;
+use crate::shared::errors::*;
+
+use super::super::article::controller::ArticleController;
+use super::super::article::model::ArticleModel;
+use super::super::category::model::CategoryModel;
+use super::super::model::FeedModel;
+use super::engine::RuleMatcher;
+use super::entities::{Rule, RuleAction, RuleChanges, RuleConditions, RuleDryRun};
+use super::model::{RuleModel, RuleRow};
+
+const DEFAULT_DRY_RUN_LIMIT: u32 = 100;
+
+pub(super) struct RuleController {
+    db: Db,
+}
+
+impl RuleController {
+    pub fn new(db: Db) -> Self {
+        RuleController { db }
+    }
+
+    ///A rule needs an action, patterns that compile and a feed or category that exists
+    fn check(connection: &Connection, conditions: &RuleConditions, actions: &[RuleAction]) -> Result<()> {
+        if actions.is_empty() {
+            return Err(error!(ErrorType::Custom(
+                "a rule needs at least one action".to_string()
+            )));
+        }
+        for action in actions {
+            if let RuleAction::Tag(name) = action {
+                if name.trim().is_empty() {
+                    return Err(error!(ErrorType::Custom("tag name can't be empty".to_string())));
+                }
+            }
+        }
+        if let Some(feed_id) = conditions.feed_id {
+            FeedModel::new(connection).get_feed(feed_id)?;
+        }
+        if let Some(category_id) = conditions.category_id {
+            CategoryModel::new(connection).get_category(category_id)?;
+        }
+        RuleMatcher::new(connection, conditions)?;
+        Ok(())
+    }
+
+    ///New rules run after the existing ones
+    pub async fn add(
+        &self,
+        title: String,
+        conditions: RuleConditions,
+        actions: Vec<RuleAction>,
+        stop_processing: bool,
+    ) -> Result<()> {
+        let now = Local::now().timestamp();
+        self.db
+            .transaction(move |transaction| {
+                Self::check(transaction, &conditions, &actions)?;
+                RuleModel::new(transaction).insert_rule(&title, &conditions, &actions, stop_processing, now)?;
+                Ok(())
+            })
+            .await
+    }
+
+    ///In the order they run, disabled ones included
+    pub async fn get_all(&self) -> Result<Vec<Rule>> {
+        let model_rules = self
+            .db
+            .call(|connection| RuleModel::new(connection).get_rules(false))
+            .await?;
+
+        Ok(model_rules.into_iter().map(Self::rule_from_row).collect())
+    }
+
+    fn rule_from_row(i: RuleRow) -> Rule {
+        Rule {
+            id: i.id,
+            title: i.title,
+            position: i.position,
+            is_enabled: i.is_enabled,
+            stop_processing: i.stop_processing,
+            conditions: i.conditions,
+            actions: i.actions,
+        }
+    }
+
+    pub async fn update(&self, rule_id: i32, changes: RuleChanges) -> Result<()> {
+        self.db
+            .transaction(move |transaction| {
+                let rule_model = RuleModel::new(transaction);
+                let mut rule = rule_model.get_rule(rule_id)?;
+                if let Some(title) = changes.title {
+                    rule.title = title;
+                }
+                if let Some(conditions) = changes.conditions {
+                    rule.conditions = conditions;
+                }
+                if let Some(actions) = changes.actions {
+                    rule.actions = actions;
+                }
+                if let Some(stop_processing) = changes.stop_processing {
+                    rule.stop_processing = stop_processing;
+                }
+                if let Some(is_enabled) = changes.is_enabled {
+                    rule.is_enabled = is_enabled;
+                }
+                if let Some(position) = changes.position {
+                    rule.position = position;
+                }
+                Self::check(transaction, &rule.conditions, &rule.actions)?;
+                rule_model.update_rule(&rule)
+            })
+            .await
+    }
+
+    pub async fn delete(&self, rule_id: i32) -> Result<()> {
+        self.db
+            .call(move |connection| RuleModel::new(connection).delete_rule(rule_id))
+            .await
+    }
+
+    ///Existing articles the conditions match, nothing is changed
+    pub async fn dry_run(&self, conditions: RuleConditions, limit: Option<u32>) -> Result<RuleDryRun> {
+        let limit = limit.unwrap_or(DEFAULT_DRY_RUN_LIMIT) as usize;
+        self.db
+            .call(move |connection| {
+                let matcher = RuleMatcher::new(connection, &conditions)?;
+                let candidates = ArticleModel::new(connection)
+                    .get_rule_candidates(None, matcher.feed_ids().as_deref())?;
+
+                let mut matched = 0;
+                let mut articles = Vec::new();
+                for candidate in candidates.into_iter().filter(|candidate| matcher.matches(candidate)) {
+                    matched += 1;
+                    if articles.len() < limit {
+                        articles.push(ArticleController::list_item_from_row(candidate.article));
+                    }
+                }
+                Ok(RuleDryRun { matched, articles })
+            })
+            .await
+    }
+}
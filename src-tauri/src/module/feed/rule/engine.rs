@@ -0,0 +1,161 @@
+use std::collections::HashSet;
+
+use regex::{Regex, RegexBuilder};
+use rusqlite::Connection;
+
+use crate::error;
+use crate::shared::errors::*;
+
+use super::super::article::model::{ArticleModel, RuleCandidateRow};
+use super::super::category::model::CategoryModel;
+use super::super::tag::model::TagModel;
+use super::entities::{ArticleNotification, RuleAction, RuleConditions};
+use super::model::RuleModel;
+
+///Conditions of a rule ready to test articles, the patterns compiled and the category turned into its feeds
+pub(in crate::module::feed) struct RuleMatcher {
+    ///Both the feed and the category when the two are given
+    feed_ids: Option<HashSet<i32>>,
+    title: Option<Regex>,
+    content: Option<Regex>,
+    author: Option<Regex>,
+    item_category: Option<Regex>,
+}
+
+impl RuleMatcher {
+    pub fn new(connection: &Connection, conditions: &RuleConditions) -> Result<Self> {
+        let mut feed_ids: Option<HashSet<i32>> = conditions.feed_id.map(|feed_id| HashSet::from([feed_id]));
+        if let Some(category_id) = conditions.category_id {
+            let category_feed_ids: HashSet<i32> = CategoryModel::new(connection)
+                .get_subtree_feed_ids(category_id)?
+                .into_iter()
+                .collect();
+            feed_ids = Some(match feed_ids {
+                Some(feed_ids) => feed_ids.intersection(&category_feed_ids).copied().collect(),
+                None => category_feed_ids,
+            });
+        }
+
+        Ok(RuleMatcher {
+            feed_ids,
+            title: pattern("title", conditions.title.as_deref())?,
+            content: pattern("content", conditions.content.as_deref())?,
+            author: pattern("author", conditions.author.as_deref())?,
+            item_category: pattern("item_category", conditions.item_category.as_deref())?,
+        })
+    }
+
+    ///Feeds the articles have to come from, None for any feed
+    pub fn feed_ids(&self) -> Option<Vec<i32>> {
+        self.feed_ids.as_ref().map(|feed_ids| feed_ids.iter().copied().collect())
+    }
+
+    ///An article without author or categories doesn't match a pattern on them
+    pub fn matches(&self, candidate: &RuleCandidateRow) -> bool {
+        let article = &candidate.article;
+        let in_feeds = match (&self.feed_ids, article.feed_id) {
+            (None, _) => true,
+            (Some(feed_ids), Some(feed_id)) => feed_ids.contains(&feed_id),
+            (Some(_), None) => false,
+        };
+        in_feeds
+            && self.title.as_ref().is_none_or(|title| title.is_match(&article.title))
+            && self.content.as_ref().is_none_or(|content| content.is_match(&candidate.content))
+            && self.author.as_ref().is_none_or(|author| {
+                article.author.as_deref().is_some_and(|name| author.is_match(name))
+            })
+            && self.item_category.as_ref().is_none_or(|item_category| {
+                candidate.categories.iter().any(|category| item_category.is_match(category))
+            })
+    }
+}
+
+///A blank pattern is no condition at all
+fn pattern(condition: &str, pattern: Option<&str>) -> Result<Option<Regex>> {
+    match pattern.filter(|pattern| !pattern.trim().is_empty()) {
+        Some(pattern) => RegexBuilder::new(pattern)
+            .case_insensitive(true)
+            .build()
+            .map(Some)
+            .map_err(|e| error!(ErrorType::RulePattern(condition.to_string(), e.to_string()))),
+        None => Ok(None),
+    }
+}
+
+struct EngineRule {
+    id: i32,
+    matcher: RuleMatcher,
+    actions: Vec<RuleAction>,
+    stop_processing: bool,
+}
+
+///The enabled rules in the order they run
+pub(in crate::module::feed) struct RuleEngine {
+    rules: Vec<EngineRule>,
+}
+
+impl RuleEngine {
+    ///A rule that doesn't compile anymore is skipped rather than failing the refresh
+    pub fn load(connection: &Connection) -> Result<Self> {
+        let mut rules = Vec::new();
+        for rule in RuleModel::new(connection).get_rules(true)? {
+            match RuleMatcher::new(connection, &rule.conditions) {
+                Ok(matcher) => rules.push(EngineRule {
+                    id: rule.id,
+                    matcher,
+                    actions: rule.actions,
+                    stop_processing: rule.stop_processing,
+                }),
+                Err(e) => eprintln!("Error loading rule {}: {}", rule.id, e),
+            }
+        }
+        Ok(RuleEngine { rules })
+    }
+
+    ///Apply the rules to articles that were just inserted, returns what has to be notified
+    ///Meant to run on the transaction that inserted them
+    pub fn run(&self, connection: &Connection, article_ids: &[i64], now: i64) -> Result<Vec<ArticleNotification>> {
+        let mut notifications = Vec::new();
+        if self.rules.is_empty() || article_ids.is_empty() {
+            return Ok(notifications);
+        }
+
+        let article_model = ArticleModel::new(connection);
+        let tag_model = TagModel::new(connection);
+        for candidate in article_model.get_rule_candidates(Some(article_ids), None)? {
+            let article_id = candidate.article.id;
+            for rule in self.rules.iter().filter(|rule| rule.matcher.matches(&candidate)) {
+                for action in &rule.actions {
+                    match action {
+                        RuleAction::MarkRead => {
+                            article_model.update_read(&[article_id], true, now)?;
+                        }
+                        RuleAction::Star => {
+                            article_model.update_starred(&[article_id], true, now)?;
+                        }
+                        RuleAction::Tag(name) => {
+                            let tag_id = tag_model.get_or_insert_tag(name.trim())?;
+                            tag_model.add_article_tag(article_id, tag_id)?;
+                        }
+                        RuleAction::Hide => {
+                            article_model.update_hidden(&[article_id], true)?;
+                        }
+                        RuleAction::Notify => notifications.push(ArticleNotification {
+                            rule_id: rule.id,
+                            feed_id: candidate.article.feed_id,
+                            article_id,
+                            title: candidate.article.title.clone(),
+                        }),
+                        RuleAction::ReadLater => {
+                            article_model.update_read_later(&[article_id], true, now)?;
+                        }
+                    }
+                }
+                if rule.stop_processing {
+                    break;
+                }
+            }
+        }
+        Ok(notifications)
+    }
+}
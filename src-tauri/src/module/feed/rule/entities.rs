@@ -0,0 +1,77 @@
+use serde::{Deserialize, Serialize};
+
+use super::super::article::entities::ArticleListItem;
+
+///What an article must look like for a rule to apply, every condition given has to match
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RuleConditions {
+    pub feed_id: Option<i32>,
+    ///Feeds of the category and its sub categories
+    pub category_id: Option<i32>,
+    ///Regular expressions, case insensitive
+    pub title: Option<String>,
+    ///Matched against the HTML of the article
+    pub content: Option<String>,
+    pub author: Option<String>,
+    ///Has to match one of the categories the feed gave the article
+    pub item_category: Option<String>,
+}
+
+///There is no delete: the next refresh would bring a deleted article back as a new one
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum RuleAction {
+    MarkRead,
+    Star,
+    ///Tag name, the tag is created when it doesn't exist yet
+    Tag(String),
+    ///Left out of the listings and counts, kept so the feed doesn't bring it back
+    ///Hidden articles have their own view and can be unhidden
+    Hide,
+    ///Sent to the front end as an `article-notification` event
+    Notify,
+    ReadLater,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Rule {
+    pub(super) id: i32,
+    pub(super) title: String,
+    ///Rules run in ascending position
+    pub(super) position: i64,
+    pub(super) is_enabled: bool,
+    ///The rules after this one are skipped for the articles it matched
+    pub(super) stop_processing: bool,
+    pub(super) conditions: RuleConditions,
+    pub(super) actions: Vec<RuleAction>,
+}
+
+///Changes to a rule, what is left to None is kept
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct RuleChanges {
+    pub title: Option<String>,
+    pub conditions: Option<RuleConditions>,
+    pub actions: Option<Vec<RuleAction>>,
+    pub stop_processing: Option<bool>,
+    pub is_enabled: Option<bool>,
+    pub position: Option<i64>,
+}
+
+///A new article matched by a rule with the notify action
+#[derive(Debug, Clone, Serialize)]
+pub struct ArticleNotification {
+    pub rule_id: i32,
+    pub feed_id: Option<i32>,
+    pub article_id: i32,
+    pub title: String,
+}
+
+///Existing articles a rule would match, newest first
+#[derive(Debug, Serialize)]
+pub struct RuleDryRun {
+    ///Every match, `articles` only has the first ones
+    pub matched: usize,
+    pub articles: Vec<ArticleListItem>,
+}
@@ -0,0 +1,6 @@
+pub(super) mod controller;
+pub(super) mod model;
+pub(super) mod entities;
+pub(in crate::module::feed) mod engine;
+pub(in crate::module::feed) mod notification;
+pub mod api;
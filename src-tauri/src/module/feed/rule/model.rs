@@ -0,0 +1,142 @@
+use crate::error;
+use crate::shared::errors::*;
+use rusqlite::Connection;
+
+use super::entities::{RuleAction, RuleConditions};
+
+pub(in crate::module::feed) struct RuleModel<'a> {
+    connection: &'a Connection,
+}
+
+pub(in crate::module::feed) struct RuleRow {
+    pub id: i32,
+    pub title: String,
+    pub position: i64,
+    pub is_enabled: bool,
+    pub stop_processing: bool,
+    pub conditions: RuleConditions,
+    pub actions: Vec<RuleAction>,
+}
+
+const RULE_COLUMNS: &str = "id, title, position, is_enabled, stop_processing, conditions, actions";
+
+impl<'a> RuleModel<'a> {
+    pub fn new(connection: &'a Connection) -> Self {
+        RuleModel { connection }
+    }
+
+    ///Conditions and actions are saved as JSON
+    pub fn insert_rule(
+        &self,
+        title: &str,
+        conditions: &RuleConditions,
+        actions: &[RuleAction],
+        stop_processing: bool,
+        created_at: i64,
+    ) -> Result<i64> {
+        let connection = self.connection;
+
+        let conditions = serde_json::to_string(conditions).map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        let actions = serde_json::to_string(actions).map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        // New rules run last
+        connection
+            .execute(
+                "INSERT INTO rule (title, position, stop_processing, conditions, actions, created_at)
+                    VALUES (?1, (SELECT COALESCE(MAX(position) + 1, 0) FROM rule), ?2, ?3, ?4, ?5)",
+                (title, stop_processing, conditions, actions, created_at),
+            )
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        Ok(connection.last_insert_rowid())
+    }
+
+    fn row_to_rule(row: &rusqlite::Row) -> rusqlite::Result<RuleRow> {
+        let conditions: String = row.get(5)?;
+        let actions: String = row.get(6)?;
+        Ok(RuleRow {
+            id: row.get(0)?,
+            title: row.get(1)?,
+            position: row.get(2)?,
+            is_enabled: row.get(3)?,
+            stop_processing: row.get(4)?,
+            conditions: serde_json::from_str(&conditions).map_err(|e| {
+                rusqlite::Error::FromSqlConversionFailure(5, rusqlite::types::Type::Text, Box::new(e))
+            })?,
+            actions: serde_json::from_str(&actions).map_err(|e| {
+                rusqlite::Error::FromSqlConversionFailure(6, rusqlite::types::Type::Text, Box::new(e))
+            })?,
+        })
+    }
+
+    pub fn get_rule(&self, rule_id: i32) -> Result<RuleRow> {
+        let connection = self.connection;
+
+        let mut statement = connection
+            .prepare_cached(&format!("SELECT {RULE_COLUMNS} FROM rule WHERE id = ?1"))
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        statement
+            .query_row([rule_id], Self::row_to_rule)
+            .map_err(|e| match e {
+                rusqlite::Error::QueryReturnedNoRows => error!(ErrorType::NotFound(format!("rule {rule_id}"))),
+                e => error!(ErrorType::Model(e.to_string())),
+            })
+    }
+
+    ///In the order they run
+    pub fn get_rules(&self, enabled_only: bool) -> Result<Vec<RuleRow>> {
+        let connection = self.connection;
+
+        let mut statement = connection
+            .prepare_cached(&format!(
+                "SELECT {RULE_COLUMNS} FROM rule WHERE is_enabled = 1 OR ?1 = 0 ORDER BY position, id"
+            ))
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        let rows = statement
+            .query_map([enabled_only], Self::row_to_rule)
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+
+        let mut rules = Vec::new();
+        for row in rows {
+            rules.push(row.map_err(|e| error!(ErrorType::Model(e.to_string())))?);
+        }
+        Ok(rules)
+    }
+
+    pub fn update_rule(&self, rule: &RuleRow) -> Result<()> {
+        let connection = self.connection;
+
+        let conditions =
+            serde_json::to_string(&rule.conditions).map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        let actions = serde_json::to_string(&rule.actions).map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        let updated = connection
+            .execute(
+                "UPDATE rule SET title = ?1, position = ?2, is_enabled = ?3, stop_processing = ?4, conditions = ?5, actions = ?6
+                    WHERE id = ?7",
+                (
+                    &rule.title,
+                    rule.position,
+                    rule.is_enabled,
+                    rule.stop_processing,
+                    conditions,
+                    actions,
+                    rule.id,
+                ),
+            )
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        if updated == 0 {
+            return Err(error!(ErrorType::NotFound(format!("rule {}", rule.id))));
+        }
+        Ok(())
+    }
+
+    pub fn delete_rule(&self, rule_id: i32) -> Result<()> {
+        let connection = self.connection;
+
+        let deleted = connection
+            .execute("DELETE FROM rule WHERE id = ?1", [rule_id])
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        if deleted == 0 {
+            return Err(error!(ErrorType::NotFound(format!("rule {rule_id}"))));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,14 @@
+use tauri::{AppHandle, Emitter};
+
+use super::entities::ArticleNotification;
+
+///Event the front end listens to, it decides how to show the notification
+pub const ARTICLE_NOTIFICATION_EVENT: &str = "article-notification";
+
+pub fn send(app: &AppHandle, notifications: &[ArticleNotification]) {
+    for notification in notifications {
+        if let Err(e) = app.emit(ARTICLE_NOTIFICATION_EVENT, notification) {
+            eprintln!("Error sending notification for article {}: {}", notification.article_id, e);
+        }
+    }
+}
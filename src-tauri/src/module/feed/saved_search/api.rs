@@ -0,0 +1,41 @@
+use crate::shared::database::Db;
+use crate::shared::errors::*;
+
+use super::controller::SavedSearchController;
+use super::entities::SavedSearch;
+
+use tauri::State;
+
+///`query` uses the search query language, see `search_articles`
+#[tauri::command]
+pub async fn add_saved_search(db: State<'_, Db>, title: String, query: String) -> Result<()> {
+    SavedSearchController::new(db.inner().clone()).add(title, query).await?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_all_saved_searches(db: State<'_, Db>) -> Result<Vec<SavedSearch>> {
+    let saved_searches = SavedSearchController::new(db.inner().clone()).get_all().await?;
+    Ok(saved_searches)
+}
+
+///Fields left to None are kept as they are
+#[tauri::command]
+pub async fn update_saved_search(
+    db: State<'_, Db>,
+    saved_search_id: i32,
+    title: Option<String>,
+    query: Option<String>,
+    position: Option<i64>,
+) -> Result<()> {
+    SavedSearchController::new(db.inner().clone())
+        .update(saved_search_id, title, query, position)
+        .await?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn delete_saved_search(db: State<'_, Db>, saved_search_id: i32) -> Result<()> {
+    SavedSearchController::new(db.inner().clone()).delete(saved_search_id).await?;
+    Ok(())
+}
@@ -0,0 +1,96 @@
+use chrono::Local;
+
+use crate::error;
+use crate::shared::database::Db;
+use crate::shared::errors::*;
+
+use super::super::article::model::ArticleModel;
+use super::super::article::query;
+use super::entities::SavedSearch;
+use super::model::SavedSearchModel;
+
+pub(super) struct SavedSearchController {
+    db: Db,
+}
+
+impl SavedSearchController {
+    pub fn new(db: Db) -> Self {
+        SavedSearchController { db }
+    }
+
+    ///A smart folder needs a query that parses and matches something
+    fn check_query(query: &str) -> Result<()> {
+        match query::parse(query)? {
+            Some(_) => Ok(()),
+            None => Err(error!(ErrorType::QueryParse("empty query".to_string(), 0, 0))),
+        }
+    }
+
+    pub async fn add(&self, title: String, query: String) -> Result<()> {
+        Self::check_query(&query)?;
+        let now = Local::now().timestamp();
+        self.db
+            .call(move |connection| {
+                SavedSearchModel::new(connection).insert_saved_search(&title, &query, now)?;
+                Ok(())
+            })
+            .await
+    }
+
+    ///Smart folders in their order, with the number of unread articles they hold
+    pub async fn get_all(&self) -> Result<Vec<SavedSearch>> {
+        self.db
+            .call(|connection| {
+                let article_model = ArticleModel::new(connection);
+                let mut saved_searches = Vec::new();
+                for i in SavedSearchModel::new(connection).get_saved_searches()? {
+                    // A query saved by an older version may not parse anymore, it is flagged
+                    let (unread, is_valid) = match query::parse(&i.query) {
+                        Ok(Some(expr)) => {
+                            (article_model.get_search_unread_count(&query::compile(&expr))?, true)
+                        }
+                        Ok(None) => (0, true),
+                        Err(_) => (0, false),
+                    };
+                    saved_searches.push(SavedSearch {
+                        id: i.id,
+                        title: i.title,
+                        query: i.query,
+                        position: i.position,
+                        unread,
+                        is_valid,
+                    });
+                }
+                Ok(saved_searches)
+            })
+            .await
+    }
+
+    pub async fn update(
+        &self,
+        saved_search_id: i32,
+        title: Option<String>,
+        query: Option<String>,
+        position: Option<i64>,
+    ) -> Result<()> {
+        if let Some(query) = &query {
+            Self::check_query(query)?;
+        }
+        self.db
+            .call(move |connection| {
+                SavedSearchModel::new(connection).update_saved_search(
+                    saved_search_id,
+                    title.as_deref(),
+                    query.as_deref(),
+                    position,
+                )
+            })
+            .await
+    }
+
+    pub async fn delete(&self, saved_search_id: i32) -> Result<()> {
+        self.db
+            .call(move |connection| SavedSearchModel::new(connection).delete_saved_search(saved_search_id))
+            .await
+    }
+}
@@ -0,0 +1,15 @@
+use serde::Serialize;
+
+///A search query kept under a name, shown with the categories as a smart folder
+#[derive(Debug, Serialize)]
+pub struct SavedSearch {
+    pub(super) id: i32,
+    pub(super) title: String,
+    pub(super) query: String,
+    ///Smart folders are shown in ascending position
+    pub(super) position: i64,
+    pub(super) unread: i64,
+    ///False when the query doesn't parse anymore, listing the folder's articles gives the parse
+    ///error, the query has to be edited
+    pub(super) is_valid: bool,
+}
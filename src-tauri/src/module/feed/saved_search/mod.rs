@@ -0,0 +1,4 @@
+pub(super) mod controller;
+pub(in crate::module::feed) mod model;
+pub(super) mod entities;
+pub mod api;
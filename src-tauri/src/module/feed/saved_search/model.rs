@@ -0,0 +1,111 @@
+use crate::error;
+use crate::shared::errors::*;
+use rusqlite::Connection;
+
+pub(in crate::module::feed) struct SavedSearchModel<'a> {
+    connection: &'a Connection,
+}
+
+pub(in crate::module::feed) struct SavedSearchRow {
+    pub id: i32,
+    pub title: String,
+    pub query: String,
+    pub position: i64,
+}
+
+impl<'a> SavedSearchModel<'a> {
+    pub fn new(connection: &'a Connection) -> Self {
+        SavedSearchModel { connection }
+    }
+
+    pub fn insert_saved_search(&self, title: &str, query: &str, created_at: i64) -> Result<i64> {
+        let connection = self.connection;
+
+        // New smart folders go last
+        connection
+            .execute(
+                "INSERT INTO saved_search (title, query, position, created_at)
+                    VALUES (?1, ?2, (SELECT COALESCE(MAX(position) + 1, 0) FROM saved_search), ?3)",
+                (title, query, created_at),
+            )
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        Ok(connection.last_insert_rowid())
+    }
+
+    fn row_to_saved_search(row: &rusqlite::Row) -> rusqlite::Result<SavedSearchRow> {
+        Ok(SavedSearchRow {
+            id: row.get(0)?,
+            title: row.get(1)?,
+            query: row.get(2)?,
+            position: row.get(3)?,
+        })
+    }
+
+    pub fn get_saved_search(&self, saved_search_id: i32) -> Result<SavedSearchRow> {
+        let connection = self.connection;
+
+        let mut statement = connection
+            .prepare_cached("SELECT id, title, query, position FROM saved_search WHERE id = ?1")
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        statement
+            .query_row([saved_search_id], Self::row_to_saved_search)
+            .map_err(|e| match e {
+                rusqlite::Error::QueryReturnedNoRows => {
+                    error!(ErrorType::NotFound(format!("saved search {saved_search_id}")))
+                }
+                e => error!(ErrorType::Model(e.to_string())),
+            })
+    }
+
+    pub fn get_saved_searches(&self) -> Result<Vec<SavedSearchRow>> {
+        let connection = self.connection;
+
+        let mut statement = connection
+            .prepare_cached("SELECT id, title, query, position FROM saved_search ORDER BY position, id")
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        let rows = statement
+            .query_map([], Self::row_to_saved_search)
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+
+        let mut saved_searches = Vec::new();
+        for row in rows {
+            saved_searches.push(row.map_err(|e| error!(ErrorType::Model(e.to_string())))?);
+        }
+        Ok(saved_searches)
+    }
+
+    ///Fields left to None keep their value
+    pub fn update_saved_search(
+        &self,
+        saved_search_id: i32,
+        title: Option<&str>,
+        query: Option<&str>,
+        position: Option<i64>,
+    ) -> Result<()> {
+        let connection = self.connection;
+
+        let updated = connection
+            .execute(
+                "UPDATE saved_search SET title = COALESCE(?1, title), query = COALESCE(?2, query), position = COALESCE(?3, position)
+                    WHERE id = ?4",
+                (title, query, position, saved_search_id),
+            )
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        if updated == 0 {
+            return Err(error!(ErrorType::NotFound(format!("saved search {saved_search_id}"))));
+        }
+        Ok(())
+    }
+
+    pub fn delete_saved_search(&self, saved_search_id: i32) -> Result<()> {
+        let connection = self.connection;
+
+        let deleted = connection
+            .execute("DELETE FROM saved_search WHERE id = ?1", [saved_search_id])
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        if deleted == 0 {
+            return Err(error!(ErrorType::NotFound(format!("saved search {saved_search_id}"))));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,71 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Local};
+
+use tauri::AppHandle;
+
+use crate::service::cron::CronSchedule;
+use crate::shared::database::Db;
+
+use super::controller::FeedController;
+use super::rule::notification;
+
+///How often the scheduler looks for feeds that are due
+const TICK: Duration = Duration::from_secs(60);
+///Longest wait between two attempts of a failing feed, unless its own schedule is longer
+const MAX_BACKOFF: i64 = 24 * 60 * 60;
+
+///Start the background loop refreshing the feeds on their own schedule
+///Notifications asked by the rules are sent through `app`
+pub fn start(app: AppHandle, db: Db) {
+    tauri::async_runtime::spawn(async move {
+        let feed_controller = FeedController::new(db);
+        loop {
+            match feed_controller.refresh_due().await {
+                Ok(results) => {
+                    for result in results.iter().filter(|result| result.error.is_some()) {
+                        eprintln!("Error refreshing feed {}: {:?}", result.feed_id, result.error);
+                    }
+                    for result in &results {
+                        notification::send(&app, &result.notifications);
+                    }
+                }
+                Err(e) => eprintln!("Error running scheduled refresh: {}", e),
+            }
+            tokio::time::sleep(TICK).await;
+        }
+    });
+}
+
+///Unix timestamp of the next fetch, the cron expression wins over the interval (minutes)
+pub(super) fn next_fetch_at(
+    refresh_interval: Option<i64>,
+    refresh_cron: Option<&str>,
+    default_interval: i64,
+    from: DateTime<Local>,
+) -> i64 {
+    let next_from_cron = refresh_cron
+        .and_then(|expression| CronSchedule::parse(expression).ok())
+        .and_then(|schedule| schedule.next_after(from));
+
+    match next_from_cron {
+        Some(next) => next.timestamp(),
+        None => from.timestamp() + refresh_interval.unwrap_or(default_interval) * 60,
+    }
+}
+
+///Push back the next fetch of a failing feed, doubling its interval for each consecutive failure
+pub(super) fn backoff_fetch_at(
+    next_fetch_at: i64,
+    from: i64,
+    error_count: i64,
+    retry_after: Option<u64>,
+) -> i64 {
+    let interval = (next_fetch_at - from).max(60);
+    let exponent = (error_count - 1).clamp(0, 16) as u32;
+    let backoff = interval
+        .saturating_mul(1 << exponent)
+        .min(MAX_BACKOFF.max(interval));
+    let retry_after = retry_after.map(|seconds| seconds as i64).unwrap_or(0);
+    from + backoff.max(retry_after)
+}
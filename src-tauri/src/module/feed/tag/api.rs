@@ -0,0 +1,64 @@
+use crate::shared::database::Db;
+use crate::shared::errors::*;
+
+use super::controller::TagController;
+use super::entities::Tag;
+
+use tauri::State;
+
+#[tauri::command]
+pub async fn add_tag(db: State<'_, Db>, name: String) -> Result<()> {
+    TagController::new(db.inner().clone()).add(name).await?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_all_tags(db: State<'_, Db>) -> Result<Vec<Tag>> {
+    let tags = TagController::new(db.inner().clone()).get_all().await?;
+    Ok(tags)
+}
+
+#[tauri::command]
+pub async fn rename_tag(db: State<'_, Db>, tag_id: i32, name: String) -> Result<()> {
+    TagController::new(db.inner().clone()).rename(tag_id, name).await?;
+    Ok(())
+}
+
+///`source_ids` are deleted, what they tagged gets `target_id`
+#[tauri::command]
+pub async fn merge_tags(db: State<'_, Db>, source_ids: Vec<i32>, target_id: i32) -> Result<()> {
+    TagController::new(db.inner().clone()).merge(source_ids, target_id).await?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn delete_tag(db: State<'_, Db>, tag_id: i32) -> Result<()> {
+    TagController::new(db.inner().clone()).delete(tag_id).await?;
+    Ok(())
+}
+
+///Tag by name, a new name creates the tag
+#[tauri::command]
+pub async fn tag_articles(db: State<'_, Db>, article_ids: Vec<i32>, name: String) -> Result<usize> {
+    let tagged = TagController::new(db.inner().clone()).tag_articles(article_ids, name).await?;
+    Ok(tagged)
+}
+
+#[tauri::command]
+pub async fn untag_articles(db: State<'_, Db>, article_ids: Vec<i32>, tag_id: i32) -> Result<usize> {
+    let untagged = TagController::new(db.inner().clone()).untag_articles(article_ids, tag_id).await?;
+    Ok(untagged)
+}
+
+///Tag by name, a new name creates the tag
+#[tauri::command]
+pub async fn tag_feed(db: State<'_, Db>, feed_id: i32, name: String) -> Result<()> {
+    TagController::new(db.inner().clone()).tag_feed(feed_id, name).await?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn untag_feed(db: State<'_, Db>, feed_id: i32, tag_id: i32) -> Result<()> {
+    TagController::new(db.inner().clone()).untag_feed(feed_id, tag_id).await?;
+    Ok(())
+}
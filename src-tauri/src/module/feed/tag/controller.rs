@@ -0,0 +1,142 @@
+use crate::error;
+use crate::shared::database::Db;
+use crate::shared::errors::*;
+
+use super::super::model::FeedModel;
+use super::entities::Tag;
+use super::model::{TagModel, TagRow};
+
+pub(super) struct TagController {
+    db: Db,
+}
+
+impl TagController {
+    pub fn new(db: Db) -> Self {
+        TagController { db }
+    }
+
+    fn check_name(name: String) -> Result<String> {
+        let name = name.trim().to_string();
+        if name.is_empty() {
+            return Err(error!(ErrorType::Custom("tag name can't be empty".to_string())));
+        }
+        Ok(name)
+    }
+
+    ///Nothing happens when the tag already exists
+    pub async fn add(&self, name: String) -> Result<()> {
+        let name = Self::check_name(name)?;
+        self.db
+            .call(move |connection| {
+                TagModel::new(connection).get_or_insert_tag(&name)?;
+                Ok(())
+            })
+            .await
+    }
+
+    ///By name, with how many articles and feeds have them
+    pub async fn get_all(&self) -> Result<Vec<Tag>> {
+        let model_tags = self
+            .db
+            .call(|connection| TagModel::new(connection).get_tags())
+            .await?;
+
+        Ok(model_tags.into_iter().map(Self::tag_from_row).collect())
+    }
+
+    fn tag_from_row(i: TagRow) -> Tag {
+        Tag {
+            id: i.id,
+            name: i.name,
+            article_count: i.article_count,
+            feed_count: i.feed_count,
+        }
+    }
+
+    ///Renaming to the name of another tag is refused, they have to be merged
+    pub async fn rename(&self, tag_id: i32, name: String) -> Result<()> {
+        let name = Self::check_name(name)?;
+        self.db
+            .transaction(move |transaction| {
+                let tag_model = TagModel::new(transaction);
+                match tag_model.find_tag_id(&name)? {
+                    Some(existing_id) if existing_id != tag_id => Err(error!(ErrorType::Custom(format!(
+                        "tag {name} already exists, merge the tags instead"
+                    )))),
+                    _ => tag_model.update_name(tag_id, &name),
+                }
+            })
+            .await
+    }
+
+    ///The source tags are deleted, their articles and feeds get the target tag
+    pub async fn merge(&self, mut source_ids: Vec<i32>, target_id: i32) -> Result<()> {
+        source_ids.sort_unstable();
+        source_ids.dedup();
+        self.db
+            .transaction(move |transaction| {
+                let tag_model = TagModel::new(transaction);
+                tag_model.get_tag(target_id)?;
+                for source_id in source_ids.into_iter().filter(|source_id| *source_id != target_id) {
+                    tag_model.get_tag(source_id)?;
+                    tag_model.merge_tag(source_id, target_id)?;
+                }
+                Ok(())
+            })
+            .await
+    }
+
+    pub async fn delete(&self, tag_id: i32) -> Result<()> {
+        self.db
+            .transaction(move |transaction| TagModel::new(transaction).delete_tag(tag_id))
+            .await
+    }
+
+    ///The tag is created when it doesn't exist, returns how many articles got it
+    pub async fn tag_articles(&self, article_ids: Vec<i32>, name: String) -> Result<usize> {
+        let name = Self::check_name(name)?;
+        self.db
+            .transaction(move |transaction| {
+                let tag_model = TagModel::new(transaction);
+                let tag_id = tag_model.get_or_insert_tag(&name)?;
+                let mut tagged = 0;
+                for article_id in article_ids {
+                    tagged += tag_model.add_article_tag(article_id, tag_id)?;
+                }
+                Ok(tagged)
+            })
+            .await
+    }
+
+    pub async fn untag_articles(&self, article_ids: Vec<i32>, tag_id: i32) -> Result<usize> {
+        self.db
+            .transaction(move |transaction| {
+                let tag_model = TagModel::new(transaction);
+                let mut untagged = 0;
+                for article_id in article_ids {
+                    untagged += tag_model.remove_article_tag(article_id, tag_id)?;
+                }
+                Ok(untagged)
+            })
+            .await
+    }
+
+    ///The tag is created when it doesn't exist
+    pub async fn tag_feed(&self, feed_id: i32, name: String) -> Result<()> {
+        let name = Self::check_name(name)?;
+        self.db
+            .transaction(move |transaction| {
+                FeedModel::new(transaction).get_feed(feed_id)?;
+                let tag_model = TagModel::new(transaction);
+                let tag_id = tag_model.get_or_insert_tag(&name)?;
+                tag_model.add_feed_tag(feed_id, tag_id)
+            })
+            .await
+    }
+
+    pub async fn untag_feed(&self, feed_id: i32, tag_id: i32) -> Result<()> {
+        self.db
+            .call(move |connection| TagModel::new(connection).remove_feed_tag(feed_id, tag_id))
+            .await
+    }
+}
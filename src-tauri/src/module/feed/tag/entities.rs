@@ -0,0 +1,11 @@
+use serde::Serialize;
+
+///A label the user puts on articles and feeds, unlike categories an article can have many
+#[derive(Debug, Serialize)]
+pub struct Tag {
+    pub(super) id: i32,
+    pub(super) name: String,
+    ///Articles tagged directly, hidden ones left out
+    pub(super) article_count: i64,
+    pub(super) feed_count: i64,
+}
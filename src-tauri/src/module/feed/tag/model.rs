@@ -0,0 +1,203 @@
+use crate::error;
+use crate::shared::errors::*;
+use rusqlite::{Connection, OptionalExtension};
+
+pub(in crate::module::feed) struct TagModel<'a> {
+    connection: &'a Connection,
+}
+
+pub(in crate::module::feed) struct TagRow {
+    pub id: i32,
+    pub name: String,
+    pub article_count: i64,
+    pub feed_count: i64,
+}
+
+const TAG_COLUMNS: &str = "tag.id, tag.name,
+    (SELECT COUNT(*) FROM article_tag_xref as xref INNER JOIN article ON article.id = xref.article_id
+        WHERE xref.tag_id = tag.id AND article.is_hidden = 0),
+    (SELECT COUNT(*) FROM feed_tag_xref WHERE tag_id = tag.id)";
+
+impl<'a> TagModel<'a> {
+    pub fn new(connection: &'a Connection) -> Self {
+        TagModel { connection }
+    }
+
+    ///Names are case insensitive, "Rust" and "rust" are the same tag
+    pub fn get_or_insert_tag(&self, name: &str) -> Result<i64> {
+        let connection = self.connection;
+
+        if let Some(tag_id) = self.find_tag_id(name)? {
+            return Ok(tag_id as i64);
+        }
+        connection
+            .prepare_cached("INSERT INTO tag (name) VALUES (?1)")
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?
+            .execute([name])
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        Ok(connection.last_insert_rowid())
+    }
+
+    fn row_to_tag(row: &rusqlite::Row) -> rusqlite::Result<TagRow> {
+        Ok(TagRow {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            article_count: row.get(2)?,
+            feed_count: row.get(3)?,
+        })
+    }
+
+    pub fn get_tag(&self, tag_id: i32) -> Result<TagRow> {
+        let connection = self.connection;
+
+        let mut statement = connection
+            .prepare_cached(&format!("SELECT {TAG_COLUMNS} FROM tag WHERE tag.id = ?1"))
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        statement
+            .query_row([tag_id], Self::row_to_tag)
+            .map_err(|e| match e {
+                rusqlite::Error::QueryReturnedNoRows => error!(ErrorType::NotFound(format!("tag {tag_id}"))),
+                e => error!(ErrorType::Model(e.to_string())),
+            })
+    }
+
+    pub fn find_tag_id(&self, name: &str) -> Result<Option<i32>> {
+        let connection = self.connection;
+
+        connection
+            .prepare_cached("SELECT id FROM tag WHERE name = ?1")
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?
+            .query_row([name], |row| row.get(0))
+            .optional()
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))
+    }
+
+    ///By name
+    pub fn get_tags(&self) -> Result<Vec<TagRow>> {
+        let connection = self.connection;
+
+        let mut statement = connection
+            .prepare_cached(&format!("SELECT {TAG_COLUMNS} FROM tag ORDER BY tag.name"))
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        let rows = statement
+            .query_map([], Self::row_to_tag)
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+
+        let mut tags = Vec::new();
+        for row in rows {
+            tags.push(row.map_err(|e| error!(ErrorType::Model(e.to_string())))?);
+        }
+        Ok(tags)
+    }
+
+    pub fn update_name(&self, tag_id: i32, name: &str) -> Result<()> {
+        let connection = self.connection;
+
+        let updated = connection
+            .execute("UPDATE tag SET name = ?1 WHERE id = ?2", (name, tag_id))
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        if updated == 0 {
+            return Err(error!(ErrorType::NotFound(format!("tag {tag_id}"))));
+        }
+        Ok(())
+    }
+
+    ///Move the articles and feeds of `source_id` to `target_id` and delete `source_id`
+    ///Meant to run on a transaction
+    pub fn merge_tag(&self, source_id: i32, target_id: i32) -> Result<()> {
+        let connection = self.connection;
+
+        for query in [
+            "INSERT OR IGNORE INTO article_tag_xref (article_id, tag_id) SELECT article_id, ?2 FROM article_tag_xref WHERE tag_id = ?1",
+            "INSERT OR IGNORE INTO feed_tag_xref (feed_id, tag_id) SELECT feed_id, ?2 FROM feed_tag_xref WHERE tag_id = ?1",
+        ] {
+            connection
+                .execute(query, (source_id, target_id))
+                .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        }
+        self.delete_tag(source_id)
+    }
+
+    ///The tag goes away from its articles and feeds, they are kept
+    pub fn delete_tag(&self, tag_id: i32) -> Result<()> {
+        let connection = self.connection;
+
+        for query in [
+            "DELETE FROM article_tag_xref WHERE tag_id = ?1",
+            "DELETE FROM feed_tag_xref WHERE tag_id = ?1",
+        ] {
+            connection
+                .execute(query, [tag_id])
+                .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        }
+        let deleted = connection
+            .execute("DELETE FROM tag WHERE id = ?1", [tag_id])
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        if deleted == 0 {
+            return Err(error!(ErrorType::NotFound(format!("tag {tag_id}"))));
+        }
+        Ok(())
+    }
+
+    ///Articles that don't exist are skipped, returns 0 when the article already had the tag
+    pub fn add_article_tag(&self, article_id: i32, tag_id: i64) -> Result<usize> {
+        let connection = self.connection;
+
+        connection
+            .prepare_cached(
+                "INSERT OR IGNORE INTO article_tag_xref (article_id, tag_id) SELECT id, ?2 FROM article WHERE id = ?1",
+            )
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?
+            .execute((article_id, tag_id))
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))
+    }
+
+    pub fn remove_article_tag(&self, article_id: i32, tag_id: i32) -> Result<usize> {
+        let connection = self.connection;
+
+        connection
+            .prepare_cached("DELETE FROM article_tag_xref WHERE article_id = ?1 AND tag_id = ?2")
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?
+            .execute((article_id, tag_id))
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))
+    }
+
+    pub fn get_article_tag_ids(&self, article_id: i32) -> Result<Vec<i32>> {
+        let connection = self.connection;
+
+        let mut statement = connection
+            .prepare_cached("SELECT tag_id FROM article_tag_xref WHERE article_id = ?1 ORDER BY tag_id")
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        let rows = statement
+            .query_map([article_id], |row| row.get::<_, i32>(0))
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+
+        let mut ids = Vec::new();
+        for row in rows {
+            ids.push(row.map_err(|e| error!(ErrorType::Model(e.to_string())))?);
+        }
+        Ok(ids)
+    }
+
+    pub fn add_feed_tag(&self, feed_id: i32, tag_id: i64) -> Result<()> {
+        let connection = self.connection;
+
+        connection
+            .prepare_cached("INSERT OR IGNORE INTO feed_tag_xref (feed_id, tag_id) VALUES (?1, ?2)")
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?
+            .execute((feed_id, tag_id))
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        Ok(())
+    }
+
+    pub fn remove_feed_tag(&self, feed_id: i32, tag_id: i32) -> Result<()> {
+        let connection = self.connection;
+
+        connection
+            .prepare_cached("DELETE FROM feed_tag_xref WHERE feed_id = ?1 AND tag_id = ?2")
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?
+            .execute((feed_id, tag_id))
+            .map_err(|e| error!(ErrorType::Model(e.to_string())))?;
+        Ok(())
+    }
+}
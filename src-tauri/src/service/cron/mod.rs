@@ -0,0 +1,268 @@
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, NaiveDateTime, TimeZone, Timelike};
+
+use crate::error;
+use crate::shared::errors::*;
+
+///Five fields cron expression: minute hour day-of-month month day-of-week
+///Each field accepts `*`, values, ranges (`1-5`), lists (`1,15`) and steps (`*/15`, `0-30/10`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct CronSchedule {
+    minutes: Vec<bool>,
+    hours: Vec<bool>,
+    days_of_month: Vec<bool>,
+    months: Vec<bool>,
+    days_of_week: Vec<bool>,
+    any_day_of_month: bool,
+    any_day_of_week: bool,
+}
+
+impl CronSchedule {
+    pub fn parse(expression: &str) -> Result<Self> {
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(error!(ErrorType::CronBadFormat(format!(
+                "expected 5 fields, got {}",
+                fields.len()
+            ))));
+        }
+
+        let mut days_of_week = Self::parse_field(fields[4], 0, 7)?;
+        // 7 is an alias for sunday
+        if days_of_week[7] {
+            days_of_week[0] = true;
+        }
+
+        Ok(CronSchedule {
+            minutes: Self::parse_field(fields[0], 0, 59)?,
+            hours: Self::parse_field(fields[1], 0, 23)?,
+            days_of_month: Self::parse_field(fields[2], 1, 31)?,
+            months: Self::parse_field(fields[3], 1, 12)?,
+            days_of_week,
+            any_day_of_month: fields[2] == "*",
+            any_day_of_week: fields[4] == "*",
+        })
+    }
+
+    fn parse_field(field: &str, min: u32, max: u32) -> Result<Vec<bool>> {
+        let bad_format = || error!(ErrorType::CronBadFormat(field.to_string()));
+        let mut values = vec![false; max as usize + 1];
+
+        for part in field.split(',') {
+            let (range, step) = match part.split_once('/') {
+                Some((range, step)) => (range, step.parse::<u32>().map_err(|_| bad_format())?),
+                None => (part, 1),
+            };
+            if step == 0 {
+                return Err(bad_format());
+            }
+
+            let (start, end) = if range == "*" {
+                (min, max)
+            } else if let Some((start, end)) = range.split_once('-') {
+                (
+                    start.parse::<u32>().map_err(|_| bad_format())?,
+                    end.parse::<u32>().map_err(|_| bad_format())?,
+                )
+            } else {
+                let value = range.parse::<u32>().map_err(|_| bad_format())?;
+                // `5/10` means from 5 to the end of the range
+                if part.contains('/') {
+                    (value, max)
+                } else {
+                    (value, value)
+                }
+            };
+
+            if start < min || end > max || start > end {
+                return Err(bad_format());
+            }
+            for value in (start..=end).step_by(step as usize) {
+                values[value as usize] = true;
+            }
+        }
+        Ok(values)
+    }
+
+    fn matches_day(&self, date: NaiveDate) -> bool {
+        let day_of_month = self.days_of_month[date.day() as usize];
+        let day_of_week = self.days_of_week[date.weekday().num_days_from_sunday() as usize];
+        // Same as cron, when both day fields are restricted either one can match
+        match (self.any_day_of_month, self.any_day_of_week) {
+            (true, true) => true,
+            (true, false) => day_of_week,
+            (false, true) => day_of_month,
+            (false, false) => day_of_month || day_of_week,
+        }
+    }
+
+    ///First time strictly after `after` matching the expression, None if nothing matches within 5 years
+    pub fn next_after(&self, after: DateTime<Local>) -> Option<DateTime<Local>> {
+        let start = after.naive_local().with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        let limit = start + Duration::days(366 * 5);
+        let mut candidate: NaiveDateTime = start;
+
+        while candidate < limit {
+            if !self.months[candidate.month() as usize] {
+                let (year, month) = match candidate.month() {
+                    12 => (candidate.year() + 1, 1),
+                    month => (candidate.year(), month + 1),
+                };
+                candidate = NaiveDate::from_ymd_opt(year, month, 1)?.and_hms_opt(0, 0, 0)?;
+                continue;
+            }
+            if !self.matches_day(candidate.date()) {
+                candidate = candidate.date().succ_opt()?.and_hms_opt(0, 0, 0)?;
+                continue;
+            }
+            if !self.hours[candidate.hour() as usize] {
+                candidate = candidate.with_minute(0)? + Duration::hours(1);
+                continue;
+            }
+            if !self.minutes[candidate.minute() as usize] {
+                candidate += Duration::minutes(1);
+                continue;
+            }
+
+            // Skip local times that don't exist because of a DST gap
+            if let Some(next) = Local.from_local_datetime(&candidate).earliest() {
+                return Some(next);
+            }
+            candidate += Duration::minutes(1);
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn local(
+        year: i32,
+        month: u32,
+        day: u32,
+        hour: u32,
+        minute: u32,
+        second: u32,
+    ) -> DateTime<Local> {
+        Local
+            .with_ymd_and_hms(year, month, day, hour, minute, second)
+            .unwrap()
+    }
+
+    fn next(expression: &str, after: DateTime<Local>) -> Option<DateTime<Local>> {
+        CronSchedule::parse(expression).unwrap().next_after(after)
+    }
+
+    #[test]
+    fn rejects_bad_expressions() {
+        for expression in [
+            "",
+            "* * * *",
+            "* * * * * *",
+            "60 * * * *",
+            "* 24 * * *",
+            "* * 0 * *",
+            "* * * 13 *",
+            "* * * * 8",
+            "*/0 * * * *",
+            "5-1 * * * *",
+            "1,,2 * * * *",
+            "a * * * *",
+        ] {
+            let error = CronSchedule::parse(expression).expect_err(expression);
+            assert!(
+                matches!(error.kind(), ErrorType::CronBadFormat(_)),
+                "{expression}"
+            );
+        }
+    }
+
+    #[test]
+    fn parses_lists_ranges_and_steps() {
+        let schedule = CronSchedule::parse("0-30/10,45 */6 1 1-3 *").unwrap();
+        let set = |values: &[bool]| {
+            values
+                .iter()
+                .enumerate()
+                .filter(|(_, set)| **set)
+                .map(|(value, _)| value)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(set(&schedule.minutes), [0, 10, 20, 30, 45]);
+        assert_eq!(set(&schedule.hours), [0, 6, 12, 18]);
+        assert_eq!(set(&schedule.days_of_month), [1]);
+        assert_eq!(set(&schedule.months), [1, 2, 3]);
+        assert!(schedule.any_day_of_week);
+
+        // A single value with a step runs to the end of the range
+        assert_eq!(
+            set(&CronSchedule::parse("50/5 * * * *").unwrap().minutes),
+            [50, 55]
+        );
+        // 7 and 0 are both sunday
+        assert_eq!(
+            CronSchedule::parse("0 0 * * 7")
+                .unwrap()
+                .next_after(local(2024, 8, 26, 12, 0, 0)),
+            CronSchedule::parse("0 0 * * 0")
+                .unwrap()
+                .next_after(local(2024, 8, 26, 12, 0, 0)),
+        );
+    }
+
+    #[test]
+    fn next_is_strictly_after() {
+        assert_eq!(
+            next("30 10 * * *", local(2024, 8, 26, 10, 29, 59)),
+            Some(local(2024, 8, 26, 10, 30, 0))
+        );
+        assert_eq!(
+            next("30 10 * * *", local(2024, 8, 26, 10, 30, 0)),
+            Some(local(2024, 8, 27, 10, 30, 0))
+        );
+        assert_eq!(
+            next("*/15 * * * *", local(2024, 8, 26, 10, 7, 0)),
+            Some(local(2024, 8, 26, 10, 15, 0))
+        );
+    }
+
+    #[test]
+    fn next_rolls_over_days_months_and_years() {
+        // Friday evening to monday morning
+        assert_eq!(
+            next("0 9 * * 1-5", local(2024, 8, 30, 10, 0, 0)),
+            Some(local(2024, 9, 2, 9, 0, 0))
+        );
+        assert_eq!(
+            next("0 0 1 * *", local(2024, 8, 26, 0, 0, 0)),
+            Some(local(2024, 9, 1, 0, 0, 0))
+        );
+        assert_eq!(
+            next("0 0 1 1 *", local(2024, 6, 1, 0, 0, 0)),
+            Some(local(2025, 1, 1, 0, 0, 0))
+        );
+        assert_eq!(
+            next("0 12 29 2 *", local(2024, 3, 1, 0, 0, 0)),
+            Some(local(2028, 2, 29, 12, 0, 0))
+        );
+    }
+
+    #[test]
+    fn restricted_day_fields_match_either() {
+        // The 13th or any friday, whichever comes first
+        assert_eq!(
+            next("0 0 13 * 5", local(2024, 9, 1, 0, 0, 0)),
+            Some(local(2024, 9, 6, 0, 0, 0))
+        );
+        assert_eq!(
+            next("0 0 13 * 5", local(2024, 9, 7, 0, 0, 0)),
+            Some(local(2024, 9, 13, 0, 0, 0))
+        );
+    }
+
+    #[test]
+    fn next_is_none_when_nothing_matches() {
+        assert_eq!(next("0 0 30 2 *", local(2024, 1, 1, 0, 0, 0)), None);
+    }
+}
@@ -1,27 +1,272 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use rand::Rng;
+
 use crate::error;
 use crate::shared::errors::*;
 use crate::shared::types::Url;
 use tauri_plugin_http::reqwest;
+use tauri_plugin_http::reqwest::header::{
+    ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED, LOCATION, RETRY_AFTER,
+};
+use tauri_plugin_http::reqwest::StatusCode;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+///Attempts made for a request failing with a transient error
+const MAX_ATTEMPTS: u32 = 3;
+///Upper bound of the first retry delay, doubled on each attempt
+const BASE_RETRY_DELAY: Duration = Duration::from_secs(1);
+///Longer Retry-After are left to the per-feed backoff instead of holding the refresh
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+const MAX_REDIRECTS: usize = 10;
+
+///Validators from a previous response, sent back so the server can answer 304 Not Modified
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CacheValidators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Redirect {
+    pub status: u16,
+    pub to: Url,
+}
+
+#[derive(Debug)]
+pub struct HttpResponse {
+    pub status: u16,
+    ///Header names are lowercased, repeated headers are joined with ", "
+    pub headers: HashMap<String, String>,
+    pub body: String,
+    pub final_url: Url,
+    ///Redirects followed from the requested url to final_url, in order
+    pub redirects: Vec<Redirect>,
+}
+
+impl HttpResponse {
+    pub fn is_not_modified(&self) -> bool {
+        self.status == reqwest::StatusCode::NOT_MODIFIED.as_u16()
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .get(&name.to_ascii_lowercase())
+            .map(|value| value.as_str())
+    }
+
+    ///Where the resource now lives if every redirect from the start of the chain was permanent (301/308)
+    pub fn permanent_url(&self) -> Option<&Url> {
+        self.redirects
+            .iter()
+            .take_while(|redirect| {
+                redirect.status == StatusCode::MOVED_PERMANENTLY.as_u16()
+                    || redirect.status == StatusCode::PERMANENT_REDIRECT.as_u16()
+            })
+            .last()
+            .map(|redirect| &redirect.to)
+    }
+
+    pub fn validators(&self) -> CacheValidators {
+        CacheValidators {
+            etag: self.header(ETAG.as_str()).map(str::to_string),
+            last_modified: self.header(LAST_MODIFIED.as_str()).map(str::to_string),
+        }
+    }
+}
 
 pub trait IHttp {
-    async fn fetch(&self, url: &Url) -> Result<String>;
+    async fn fetch(&self, url: &Url) -> Result<HttpResponse>;
+    async fn fetch_conditional(
+        &self,
+        url: &Url,
+        validators: &CacheValidators,
+    ) -> Result<HttpResponse>;
 }
 pub struct HttpReqwest {}
 
 impl IHttp for HttpReqwest {
-    async fn fetch(&self, url: &Url) -> Result<String> {
-        let response = reqwest::get(url)
-            .await
+    async fn fetch(&self, url: &Url) -> Result<HttpResponse> {
+        self.fetch_conditional(url, &CacheValidators::default()).await
+    }
+
+    async fn fetch_conditional(
+        &self,
+        url: &Url,
+        validators: &CacheValidators,
+    ) -> Result<HttpResponse> {
+        let client = Self::client(REQUEST_TIMEOUT)?;
+
+        let mut attempt = 1;
+        loop {
+            let error = match self.send(&client, url, validators).await {
+                Ok(response) => return Ok(response),
+                Err(error) => error,
+            };
+            if attempt >= MAX_ATTEMPTS {
+                return Err(error);
+            }
+            match Self::retry_delay(&error, attempt) {
+                Some(delay) => tokio::time::sleep(delay).await,
+                None => return Err(error),
+            }
+            attempt += 1;
+        }
+    }
+}
+
+impl HttpReqwest {
+    ///A single attempt with its own timeout, for requests that are expected to fail often
+    pub async fn fetch_once(&self, url: &Url, timeout: Duration) -> Result<HttpResponse> {
+        let client = Self::client(timeout)?;
+        self.send(&client, url, &CacheValidators::default()).await
+    }
+
+    fn client(timeout: Duration) -> Result<reqwest::Client> {
+        // Redirects are followed by hand so the chain can be reported
+        reqwest::Client::builder()
+            .timeout(timeout)
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .map_err(|e| error!(ErrorType::ReqwestBadResponse(e.to_string())))
+    }
+
+    async fn send(
+        &self,
+        client: &reqwest::Client,
+        url: &Url,
+        validators: &CacheValidators,
+    ) -> Result<HttpResponse> {
+        let mut current_url = reqwest::Url::parse(url)
             .map_err(|e| error!(ErrorType::ReqwestBadUrl(e.to_string())))?;
+        let mut redirects = Vec::new();
+
+        let response = loop {
+            let mut request = client.get(current_url.clone());
+            if let Some(etag) = &validators.etag {
+                request = request.header(IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &validators.last_modified {
+                request = request.header(IF_MODIFIED_SINCE, last_modified);
+            }
 
-        // if !response.status().is_success() {//might be good to check the status before going further
-        //     return Err(Error::ReqwestBadStatus(response.status().as_u16()));
-        // }
+            let response = request.send().await.map_err(|e| {
+                if e.is_timeout() {
+                    error!(ErrorType::HttpTimeout)
+                } else if e.is_connect() {
+                    error!(ErrorType::HttpConnection(e.to_string()))
+                } else {
+                    error!(ErrorType::ReqwestBadUrl(e.to_string()))
+                }
+            })?;
+
+            let status = response.status();
+            let location = response
+                .headers()
+                .get(LOCATION)
+                .and_then(|value| value.to_str().ok());
+            let location = match location {
+                Some(location) if status.is_redirection() && status != StatusCode::NOT_MODIFIED => {
+                    location
+                }
+                _ => break response,
+            };
+
+            if redirects.len() >= MAX_REDIRECTS {
+                return Err(error!(ErrorType::HttpTooManyRedirects));
+            }
+            let next_url = current_url
+                .join(location)
+                .map_err(|e| error!(ErrorType::ReqwestBadUrl(e.to_string())))?;
+            redirects.push(Redirect {
+                status: status.as_u16(),
+                to: next_url.to_string(),
+            });
+            current_url = next_url;
+        };
+
+        let status = response.status();
+        if !status.is_success() && status != StatusCode::NOT_MODIFIED {
+            let retry_after = response
+                .headers()
+                .get(RETRY_AFTER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(Self::parse_retry_after);
+            return Err(error!(match status {
+                StatusCode::UNAUTHORIZED => ErrorType::HttpUnauthorized,
+                StatusCode::FORBIDDEN => ErrorType::HttpForbidden,
+                StatusCode::NOT_FOUND => ErrorType::HttpNotFound,
+                StatusCode::GONE => ErrorType::HttpGone,
+                StatusCode::TOO_MANY_REQUESTS => ErrorType::HttpTooManyRequests(retry_after),
+                status if status.is_server_error() => {
+                    ErrorType::HttpServerError(status.as_u16(), retry_after)
+                }
+                status => ErrorType::HttpClientError(status.as_u16()),
+            }));
+        }
+
+        let final_url = response.url().to_string();
+        let mut headers: HashMap<String, String> = HashMap::new();
+        for (name, value) in response.headers() {
+            let Ok(value) = value.to_str() else {
+                continue;
+            };
+            headers
+                .entry(name.as_str().to_string())
+                .and_modify(|existing| {
+                    existing.push_str(", ");
+                    existing.push_str(value);
+                })
+                .or_insert_with(|| value.to_string());
+        }
+
+        let body = response.text().await.map_err(|e| {
+            if e.is_timeout() {
+                error!(ErrorType::HttpTimeout)
+            } else {
+                error!(ErrorType::ReqwestBadResponse(e.to_string()))
+            }
+        })?;
+
+        Ok(HttpResponse {
+            status: status.as_u16(),
+            headers,
+            body,
+            final_url,
+            redirects,
+        })
+    }
+
+    ///Delay before the next attempt, None when the error isn't worth retrying right away
+    fn retry_delay(error: &Error, attempt: u32) -> Option<Duration> {
+        match error.kind() {
+            ErrorType::HttpTooManyRequests(Some(seconds))
+            | ErrorType::HttpServerError(_, Some(seconds)) => {
+                let delay = Duration::from_secs(*seconds);
+                (delay <= MAX_RETRY_DELAY).then_some(delay)
+            }
+            ErrorType::HttpTimeout
+            | ErrorType::HttpConnection(_)
+            | ErrorType::HttpTooManyRequests(None)
+            | ErrorType::HttpServerError(_, None) => {
+                // Full jitter so feeds failing together don't retry in lockstep
+                let max = BASE_RETRY_DELAY.saturating_mul(1 << (attempt - 1)).min(MAX_RETRY_DELAY);
+                let millis = rand::thread_rng().gen_range(0..=max.as_millis() as u64);
+                Some(Duration::from_millis(millis))
+            }
+            _ => None,
+        }
+    }
 
-        let content = response
-            .text()
-            .await
-            .map_err(|e| error!(ErrorType::ReqwestBadResponse(e.to_string())))?;
-        Ok(content)
+    ///Retry-After is either a number of seconds or an HTTP date
+    fn parse_retry_after(value: &str) -> Option<u64> {
+        let value = value.trim();
+        if let Ok(seconds) = value.parse::<u64>() {
+            return Some(seconds);
+        }
+        let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+        let seconds = date.timestamp() - chrono::Utc::now().timestamp();
+        Some(seconds.max(0) as u64)
     }
 }
@@ -1,51 +1,231 @@
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
 use crate::error;
 use crate::shared::errors::*;
-use rusqlite::Connection;
-
-pub trait IDb {
-    fn new() -> Self;
-    fn open(&mut self) -> Result<&mut Self>;
-    fn close(&mut self) -> Result<()>;
-}
+use crate::shared::html::plain_text;
+use rusqlite::functions::FunctionFlags;
+use rusqlite::{Connection, Transaction};
+use tauri::{AppHandle, Manager};
 
+///Handle to the app's database, registered with `app.manage` and shared by the commands and the scheduler
+///Queries run on the blocking thread pool so they never hold up the async runtime
+#[derive(Clone)]
 pub struct Db {
-    pub connection: Option<Connection>, // state: DbConnection,
+    connection: Arc<Mutex<Connection>>,
 }
 
-impl IDb for Db {
-    fn new() -> Self {
-        Db { connection: None }
+const STATEMENT_CACHE_CAPACITY: usize = 64;
+const DATABASE_FILE: &str = "db.sqlite";
+///A file with this name next to the executable keeps the database there instead of the app data directory
+const PORTABLE_MARKER: &str = "portable";
+const DATABASE_ARG: &str = "--database";
+
+///Schema migrations in order, a database's `user_version` is the number of them it has been through
+///Shipped migrations are never edited or renumbered, a correction goes in a new one
+const MIGRATIONS: [&str; 13] = [
+    include_str!("../../assets/sql/migrations/0001_init.sqlite3-query"),
+    include_str!("../../assets/sql/migrations/0002_article_guid.sqlite3-query"),
+    include_str!("../../assets/sql/migrations/0003_feed_refresh.sqlite3-query"),
+    include_str!("../../assets/sql/migrations/0004_article_metadata.sqlite3-query"),
+    include_str!("../../assets/sql/migrations/0005_article_state.sqlite3-query"),
+    include_str!("../../assets/sql/migrations/0006_article_listing.sqlite3-query"),
+    include_str!("../../assets/sql/migrations/0007_feed_link.sqlite3-query"),
+    include_str!("../../assets/sql/migrations/0008_feed_metadata.sqlite3-query"),
+    include_str!("../../assets/sql/migrations/0009_article_search.sqlite3-query"),
+    include_str!("../../assets/sql/migrations/0010_saved_search.sqlite3-query"),
+    include_str!("../../assets/sql/migrations/0011_rules.sqlite3-query"),
+    include_str!("../../assets/sql/migrations/0012_feed_tag.sqlite3-query"),
+    include_str!("../../assets/sql/migrations/0013_article_search_text.sqlite3-query"),
+];
+
+impl Db {
+    ///Where the database lives: `--database <path>`, next to the executable in portable mode,
+    ///or the platform app data directory
+    pub fn resolve_path(app: &AppHandle) -> Result<PathBuf> {
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            if arg == DATABASE_ARG {
+                let path = args.next().ok_or(error!(ErrorType::Db(format!(
+                    "{DATABASE_ARG} expects a path"
+                ))))?;
+                return Ok(PathBuf::from(path));
+            }
+            if let Some(path) = arg.strip_prefix(&format!("{DATABASE_ARG}=")) {
+                return Ok(PathBuf::from(path));
+            }
+        }
+
+        let executable_dir = std::env::current_exe()
+            .ok()
+            .and_then(|executable| executable.parent().map(Path::to_path_buf));
+        if let Some(executable_dir) = executable_dir {
+            if executable_dir.join(PORTABLE_MARKER).exists() {
+                return Ok(executable_dir.join(DATABASE_FILE));
+            }
+        }
+
+        let app_data_dir = app
+            .path()
+            .app_data_dir()
+            .map_err(|e| error!(ErrorType::Db(e.to_string())))?;
+        Ok(app_data_dir.join(DATABASE_FILE))
+    }
+
+    ///Open the database, creating it or bringing it up to the schema this build expects
+    pub fn setup(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent).map_err(|e| error!(ErrorType::Db(e.to_string())))?;
+        }
+        Self::move_legacy_database(path)?;
+
+        let mut connection =
+            Connection::open(path).map_err(|e| error!(ErrorType::Db(e.to_string())))?;
+        // Readers still wait for writers on the one shared connection, WAL is for cheaper commits:
+        // with synchronous NORMAL they don't sync the database file and an app crash loses nothing
+        connection
+            .pragma_update(None, "journal_mode", "WAL")
+            .map_err(|e| error!(ErrorType::Db(e.to_string())))?;
+        connection
+            .pragma_update(None, "synchronous", "NORMAL")
+            .map_err(|e| error!(ErrorType::Db(e.to_string())))?;
+        connection.set_prepared_statement_cache_capacity(STATEMENT_CACHE_CAPACITY);
+        Self::add_functions(&connection)?;
+        Self::migrate(&mut connection)?;
+
+        Ok(Db {
+            connection: Arc::new(Mutex::new(connection)),
+        })
     }
 
-    fn open(&mut self) -> Result<&mut Self> {
-        let path = "db.sqlite";
-        let connection = Connection::open(path).map_err(|e| error!(ErrorType::Db(e.to_string())))?;
-        self.connection = Some(connection);
-        Ok(self)
+    ///Run `f` with the connection on a blocking thread, one caller at a time
+    pub async fn call<T, F>(&self, f: F) -> Result<T>
+    where
+        F: FnOnce(&mut Connection) -> Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let connection = self.connection.clone();
+        tauri::async_runtime::spawn_blocking(move || {
+            let mut connection = connection
+                .lock()
+                .map_err(|e| error!(ErrorType::Db(e.to_string())))?;
+            f(&mut connection)
+        })
+        .await
+        .map_err(|e| error!(ErrorType::Db(e.to_string())))?
     }
 
-    fn close(&mut self) -> Result<()> {
-        if let Some(connection) = self.connection.take() {
-            connection.close().unwrap();
+    ///Same as `call` inside a transaction, committed when `f` succeeds and rolled back otherwise
+    pub async fn transaction<T, F>(&self, f: F) -> Result<T>
+    where
+        F: FnOnce(&Transaction) -> Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        self.call(move |connection| {
+            let transaction = connection
+                .transaction()
+                .map_err(|e| error!(ErrorType::Db(e.to_string())))?;
+            let value = f(&transaction)?;
+            transaction
+                .commit()
+                .map_err(|e| error!(ErrorType::Db(e.to_string())))?;
+            Ok(value)
+        })
+        .await
+    }
+
+    ///Earlier versions kept the database in the working directory, bring it over on first launch
+    fn move_legacy_database(path: &Path) -> Result<()> {
+        let legacy_path = Path::new(DATABASE_FILE);
+        if path.exists() || !legacy_path.is_file() {
+            return Ok(());
+        }
+        let same_file = match (legacy_path.canonicalize(), path.parent().map(Path::canonicalize)) {
+            (Ok(legacy_path), Some(Ok(parent))) => parent.join(DATABASE_FILE) == legacy_path,
+            _ => false,
+        };
+        if same_file {
+            return Ok(());
+        }
+
+        for suffix in ["", "-wal", "-shm"] {
+            let from = PathBuf::from(format!("{DATABASE_FILE}{suffix}"));
+            if !from.exists() {
+                continue;
+            }
+            let mut to = path.as_os_str().to_owned();
+            to.push(suffix);
+            // rename doesn't work across file systems, copy then
+            if std::fs::rename(&from, &to).is_err() {
+                std::fs::copy(&from, &to).map_err(|e| error!(ErrorType::Db(e.to_string())))?;
+                std::fs::remove_file(&from).map_err(|e| error!(ErrorType::Db(e.to_string())))?;
+            }
         }
         Ok(())
     }
-}
 
-impl Db {
-    pub fn setup() {
-        let path = "db.sqlite";
-        if !std::path::Path::exists(std::path::Path::new(&path)) {
-            let connection = Connection::open(path).expect("error with connection open");
+    ///SQL functions the migrations use
+    fn add_functions(connection: &Connection) -> Result<()> {
+        connection
+            .create_scalar_function(
+                "plain_text",
+                1,
+                FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+                |context| {
+                    let html = context.get::<Option<String>>(0)?;
+                    Ok(html.map(|html| plain_text(&html)))
+                },
+            )
+            .map_err(|e| error!(ErrorType::Db(e.to_string())))
+    }
 
-            let queries = std::fs::read_to_string("assets/sql/init.sqlite3-query")
-                .expect("sql init file doesn't exists");
-            let queries = queries.as_str();
+    fn migrate(connection: &mut Connection) -> Result<()> {
+        let latest = MIGRATIONS.len() as i64;
+        let mut version = Self::schema_version(connection)?;
+        if version > latest {
+            return Err(error!(ErrorType::DbVersionTooNew(version, latest)));
+        }
 
+        // Databases created before migrations have the first schema but no version
+        if version == 0 && Self::has_table(connection, "article")? {
             connection
-                .execute_batch(queries)
-                .expect("ERROR RUNNING QUERIES");
-            connection.close().expect("ERROR CLOSING");
+                .pragma_update(None, "user_version", 1)
+                .map_err(|e| error!(ErrorType::Db(e.to_string())))?;
+            version = 1;
         }
+
+        for (index, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+            let transaction = connection
+                .transaction()
+                .map_err(|e| error!(ErrorType::Db(e.to_string())))?;
+            transaction
+                .execute_batch(migration)
+                .map_err(|e| error!(ErrorType::DbMigration(index as i64 + 1, e.to_string())))?;
+            // user_version is part of the transaction, a failed migration leaves it untouched
+            transaction
+                .pragma_update(None, "user_version", index as i64 + 1)
+                .map_err(|e| error!(ErrorType::Db(e.to_string())))?;
+            transaction
+                .commit()
+                .map_err(|e| error!(ErrorType::DbMigration(index as i64 + 1, e.to_string())))?;
+        }
+        Ok(())
+    }
+
+    fn schema_version(connection: &Connection) -> Result<i64> {
+        connection
+            .pragma_query_value(None, "user_version", |row| row.get(0))
+            .map_err(|e| error!(ErrorType::Db(e.to_string())))
+    }
+
+    fn has_table(connection: &Connection, name: &str) -> Result<bool> {
+        connection
+            .query_row(
+                "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = ?1",
+                [name],
+                |row| row.get::<_, i64>(0),
+            )
+            .map(|count| count > 0)
+            .map_err(|e| error!(ErrorType::Db(e.to_string())))
     }
 }
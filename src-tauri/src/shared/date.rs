@@ -0,0 +1,291 @@
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+
+const MONTHS: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+
+///Unix timestamp (UTC) of a feed date, None when it can't be understood
+///Handles RFC 3339 / W3C dates (Atom, JSON Feed, dc:date) and RFC 822/2822 dates (RSS)
+///including the usual broken variants: no weekday, named timezones, two digits years
+pub fn parse_timestamp(value: &str) -> Option<i64> {
+    let value = value.trim();
+    if value.is_empty() {
+        return None;
+    }
+    parse_rfc3339(value)
+        .or_else(|| parse_rfc2822(value))
+        .map(|date| date.timestamp())
+}
+
+fn parse_rfc3339(value: &str) -> Option<DateTime<Utc>> {
+    if let Ok(date) = DateTime::parse_from_rfc3339(value) {
+        return Some(date.with_timezone(&Utc));
+    }
+    if !value.as_bytes().first()?.is_ascii_digit() {
+        return None;
+    }
+
+    // W3C profile: the time, seconds and offset are optional, a space may replace the T
+    let value = value.replacen(' ', "T", 1);
+    let (date, time) = value.split_once(['T', 't']).unwrap_or((&value, ""));
+    let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()?;
+    if time.is_empty() {
+        return Some(Utc.from_utc_datetime(&date.and_time(NaiveTime::MIN)));
+    }
+
+    let offset_start = time.find(['Z', 'z', '+', '-']).unwrap_or(time.len());
+    let (time, offset) = time.split_at(offset_start);
+    let time = NaiveTime::parse_from_str(time, "%H:%M:%S%.f")
+        .or_else(|_| NaiveTime::parse_from_str(time, "%H:%M"))
+        .ok()?;
+    let offset = match offset {
+        "" | "Z" | "z" => FixedOffset::east_opt(0)?,
+        offset => parse_offset(offset)?,
+    };
+    let date = offset.from_local_datetime(&date.and_time(time)).single()?;
+    Some(date.with_timezone(&Utc))
+}
+
+fn parse_rfc2822(value: &str) -> Option<DateTime<Utc>> {
+    if let Ok(date) = DateTime::parse_from_rfc2822(value) {
+        return Some(date.with_timezone(&Utc));
+    }
+
+    let cleaned = value.replace(',', " ");
+    let mut tokens: Vec<&str> = cleaned.split_whitespace().collect();
+    // The weekday carries no information and is often misspelled
+    if tokens.first()?.chars().all(|c| c.is_ascii_alphabetic()) && month(tokens[0]).is_none() {
+        tokens.remove(0);
+    }
+    if tokens.len() < 3 {
+        return None;
+    }
+
+    // Some feeds put the month first: "Aug 26 2024"
+    let (day, month) = match (tokens[0].parse::<u32>(), month(tokens[0])) {
+        (Ok(day), _) => (day, month(tokens[1])?),
+        (_, Some(month)) => (tokens[1].parse::<u32>().ok()?, month),
+        _ => return None,
+    };
+    let year = match tokens[2].parse::<i32>().ok()? {
+        year if tokens[2].len() <= 2 && year < 50 => year + 2000,
+        year if tokens[2].len() <= 3 => year + 1900,
+        year => year,
+    };
+    let date = NaiveDate::from_ymd_opt(year, month, day)?;
+
+    let time = match tokens.get(3) {
+        Some(time) => NaiveTime::parse_from_str(time, "%H:%M:%S")
+            .or_else(|_| NaiveTime::parse_from_str(time, "%H:%M"))
+            .ok()?,
+        None => NaiveTime::MIN,
+    };
+    // Unknown zone names are taken as UTC rather than losing the whole date
+    let offset = match tokens.get(4) {
+        Some(zone) => parse_offset(zone)
+            .or_else(|| zone_offset(zone))
+            .unwrap_or(FixedOffset::east_opt(0)?),
+        None => FixedOffset::east_opt(0)?,
+    };
+    let date = offset
+        .from_local_datetime(&NaiveDateTime::new(date, time))
+        .single()?;
+    Some(date.with_timezone(&Utc))
+}
+
+fn month(token: &str) -> Option<u32> {
+    let token = token.to_ascii_lowercase();
+    let prefix = token.get(..3)?;
+    MONTHS
+        .iter()
+        .position(|month| *month == prefix)
+        .map(|index| index as u32 + 1)
+}
+
+///`+0200`, `+02:00`, `-02`
+fn parse_offset(offset: &str) -> Option<FixedOffset> {
+    let sign = match offset.chars().next()? {
+        '+' => 1,
+        '-' => -1,
+        _ => return None,
+    };
+    let digits: String = offset[1..].chars().filter(|c| *c != ':').collect();
+    if !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let (hours, minutes) = match digits.len() {
+        2 => (digits.parse::<i32>().ok()?, 0),
+        4 => (digits[..2].parse::<i32>().ok()?, digits[2..].parse::<i32>().ok()?),
+        _ => return None,
+    };
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+}
+
+fn zone_offset(zone: &str) -> Option<FixedOffset> {
+    let hours = match zone.to_ascii_uppercase().as_str() {
+        "UT" | "UTC" | "GMT" | "Z" | "WET" => 0.0,
+        "BST" | "CET" | "WEST" | "MET" => 1.0,
+        "CEST" | "EET" | "MEST" | "SAST" => 2.0,
+        "EEST" | "MSK" => 3.0,
+        "IST" => 5.5,
+        "SGT" | "HKT" | "AWST" => 8.0,
+        "JST" | "KST" => 9.0,
+        "ACST" => 9.5,
+        "AEST" => 10.0,
+        "AEDT" => 11.0,
+        "NZST" => 12.0,
+        "NZDT" => 13.0,
+        "EDT" => -4.0,
+        "EST" | "CDT" => -5.0,
+        "CST" | "MDT" => -6.0,
+        "MST" | "PDT" => -7.0,
+        "PST" | "AKDT" => -8.0,
+        "AKST" => -9.0,
+        "HST" => -10.0,
+        "AST" => -4.0,
+        "ADT" => -3.0,
+        "NST" => -3.5,
+        _ => return None,
+    };
+    FixedOffset::east_opt((hours * 3600.0) as i32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(year: i32, month: u32, day: u32, hour: u32, minute: u32, second: u32) -> Option<i64> {
+        Some(
+            Utc.with_ymd_and_hms(year, month, day, hour, minute, second)
+                .unwrap()
+                .timestamp(),
+        )
+    }
+
+    #[test]
+    fn parses_rfc3339() {
+        assert_eq!(
+            parse_timestamp("2024-08-26T10:15:30Z"),
+            utc(2024, 8, 26, 10, 15, 30)
+        );
+        assert_eq!(
+            parse_timestamp("2024-08-26T10:15:30.250+02:00"),
+            utc(2024, 8, 26, 8, 15, 30)
+        );
+        assert_eq!(
+            parse_timestamp("  2024-08-26T10:15:30-05:00\n"),
+            utc(2024, 8, 26, 15, 15, 30)
+        );
+    }
+
+    #[test]
+    fn parses_partial_w3c_dates() {
+        assert_eq!(parse_timestamp("2024-08-26"), utc(2024, 8, 26, 0, 0, 0));
+        assert_eq!(
+            parse_timestamp("2024-08-26T10:15+01:00"),
+            utc(2024, 8, 26, 9, 15, 0)
+        );
+        assert_eq!(
+            parse_timestamp("2024-08-26 10:15:30"),
+            utc(2024, 8, 26, 10, 15, 30)
+        );
+        assert_eq!(
+            parse_timestamp("2024-08-26T10:15:30+0200"),
+            utc(2024, 8, 26, 8, 15, 30)
+        );
+    }
+
+    #[test]
+    fn parses_rfc2822() {
+        assert_eq!(
+            parse_timestamp("Mon, 26 Aug 2024 10:15:30 +0000"),
+            utc(2024, 8, 26, 10, 15, 30)
+        );
+        assert_eq!(
+            parse_timestamp("Mon, 26 Aug 2024 10:15:30 GMT"),
+            utc(2024, 8, 26, 10, 15, 30)
+        );
+        assert_eq!(
+            parse_timestamp("Mon, 26 Aug 2024 10:15:30 -0400"),
+            utc(2024, 8, 26, 14, 15, 30)
+        );
+    }
+
+    #[test]
+    fn parses_broken_rfc2822() {
+        // No weekday, misspelled weekday, no seconds, no time
+        assert_eq!(
+            parse_timestamp("26 Aug 2024 10:15:30 +0000"),
+            utc(2024, 8, 26, 10, 15, 30)
+        );
+        assert_eq!(
+            parse_timestamp("Mnd, 26 Aug 2024 10:15:30 +0000"),
+            utc(2024, 8, 26, 10, 15, 30)
+        );
+        assert_eq!(
+            parse_timestamp("Mon, 26 Aug 2024 10:15 +0000"),
+            utc(2024, 8, 26, 10, 15, 0)
+        );
+        assert_eq!(
+            parse_timestamp("Mon, 26 August 2024"),
+            utc(2024, 8, 26, 0, 0, 0)
+        );
+        // Month first
+        assert_eq!(
+            parse_timestamp("Aug 26 2024 10:15:30 +0000"),
+            utc(2024, 8, 26, 10, 15, 30)
+        );
+        // Offset with a colon or hours only
+        assert_eq!(
+            parse_timestamp("Mon, 26 Aug 2024 10:15:30 +02:00"),
+            utc(2024, 8, 26, 8, 15, 30)
+        );
+        assert_eq!(
+            parse_timestamp("Mon, 26 Aug 2024 10:15:30 -02"),
+            utc(2024, 8, 26, 12, 15, 30)
+        );
+    }
+
+    #[test]
+    fn parses_named_zones() {
+        assert_eq!(
+            parse_timestamp("Mon, 26 Aug 2024 10:15:30 CEST"),
+            utc(2024, 8, 26, 8, 15, 30)
+        );
+        assert_eq!(
+            parse_timestamp("Mon, 26 Aug 2024 10:15:30 PDT"),
+            utc(2024, 8, 26, 17, 15, 30)
+        );
+        assert_eq!(
+            parse_timestamp("Mon, 26 Aug 2024 10:15:30 IST"),
+            utc(2024, 8, 26, 4, 45, 30)
+        );
+        // Unknown zones are taken as UTC
+        assert_eq!(
+            parse_timestamp("Mon, 26 Aug 2024 10:15:30 XYZ"),
+            utc(2024, 8, 26, 10, 15, 30)
+        );
+    }
+
+    #[test]
+    fn parses_two_digit_years() {
+        assert_eq!(
+            parse_timestamp("Mon, 26 Aug 24 10:15:30 +0000"),
+            utc(2024, 8, 26, 10, 15, 30)
+        );
+        assert_eq!(
+            parse_timestamp("Thu, 26 Aug 99 10:15:30 +0000"),
+            utc(1999, 8, 26, 10, 15, 30)
+        );
+    }
+
+    #[test]
+    fn rejects_garbage() {
+        assert_eq!(parse_timestamp(""), None);
+        assert_eq!(parse_timestamp("   "), None);
+        assert_eq!(parse_timestamp("yesterday"), None);
+        assert_eq!(parse_timestamp("2024-13-40"), None);
+        assert_eq!(parse_timestamp("Mon, 31 Feb 2024 10:15:30 +0000"), None);
+        assert_eq!(parse_timestamp("Mon, 26 Aug 2024 25:15:30 +0000"), None);
+    }
+}
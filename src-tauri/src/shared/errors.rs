@@ -1,6 +1,8 @@
 use serde::Serialize;
 use std::convert::From;
 
+use crate::shared::types::FeedCandidate;
+
 pub type Result<T> = core::result::Result<T, Error>;
 
 // Convenience macro for creating errors
@@ -16,11 +18,40 @@ pub enum ErrorType {
     Custom(String),
     ReqwestBadUrl(String),
     ReqwestBadResponse(String),
+    HttpTimeout,
+    HttpConnection(String),
+    HttpTooManyRedirects,
+    HttpUnauthorized,
+    HttpForbidden,
+    HttpNotFound,
+    HttpGone,
+    ///Seconds to wait before retrying when the server sent a Retry-After
+    HttpTooManyRequests(Option<u64>),
+    HttpClientError(u16),
+    ///Status code and Retry-After seconds
+    HttpServerError(u16, Option<u64>),
     XmlBadFormat,
+    ///The url is a web page, these are the feeds it links to
+    NotAFeed(Vec<FeedCandidate>),
     XmlDeserialize(String),
+    JsonDeserialize(String),
     Model(String),
+    ///What wasn't found, e.g. "feed 12"
+    NotFound(String),
+    ///Category and the would-be parent that already sits below it
+    CategoryCycle(i32, i32),
+    ///Path and io error of a file the user picked
+    File(String, String),
     Db(String),
-    Entity(String),
+    ///Version of the failing migration and the sqlite error
+    DbMigration(i64, String),
+    ///Version of the database and latest version known by the app, the app is older than the data
+    DbVersionTooNew(i64, i64),
+    CronBadFormat(String),
+    ///Message and character range of the bad part of a search query
+    QueryParse(String, usize, usize),
+    ///Condition and regex error of a rule pattern that doesn't compile
+    RulePattern(String, String),
     // Controller(String),
     // Xml(XmlDeError)
 }
@@ -45,6 +76,10 @@ impl Error {
             context: ErrorContext { file, line, column },
         }
     }
+
+    pub fn kind(&self) -> &ErrorType {
+        &self.error
+    }
 }
 
 impl core::fmt::Display for Error {
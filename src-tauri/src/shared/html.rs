@@ -0,0 +1,51 @@
+use regex::{Captures, Regex};
+use std::sync::LazyLock;
+
+///Elements whose text isn't shown, comments, and every other tag
+static MARKUP: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?is)<script\b.*?</script\s*>|<style\b.*?</style\s*>|<!--.*?-->|<[^>]*>")
+        .expect("valid regex")
+});
+static ENTITY: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"&(#[0-9]+|#[xX][0-9a-fA-F]+|[A-Za-z]+);").expect("valid regex"));
+static WHITESPACE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\s+").expect("valid regex"));
+
+///Text of an html fragment as it reads, for the search index
+///Tags become spaces so `<p>a</p><p>b</p>` doesn't glue the words together
+pub fn plain_text(html: &str) -> String {
+    let text = MARKUP.replace_all(html, " ");
+    let text = ENTITY.replace_all(&text, |captures: &Captures| {
+        decode_entity(&captures[1]).unwrap_or_else(|| captures[0].to_string())
+    });
+    WHITESPACE.replace_all(&text, " ").trim().to_string()
+}
+
+///The common named entities and every numeric one, None for the others
+fn decode_entity(entity: &str) -> Option<String> {
+    let code = match entity.strip_prefix('#') {
+        Some(number) => match number.strip_prefix(['x', 'X']) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+            None => number.parse().ok()?,
+        },
+        None => {
+            let text = match entity {
+                "amp" => "&",
+                "lt" => "<",
+                "gt" => ">",
+                "quot" => "\"",
+                "apos" => "'",
+                "nbsp" => " ",
+                "ndash" => "–",
+                "mdash" => "—",
+                "hellip" => "…",
+                "lsquo" => "‘",
+                "rsquo" => "’",
+                "ldquo" => "“",
+                "rdquo" => "”",
+                _ => return None,
+            };
+            return Some(text.to_string());
+        }
+    };
+    char::from_u32(code).map(String::from)
+}
@@ -1,3 +1,5 @@
 pub mod database;
+pub mod date;
+pub mod html;
 pub mod types;
 pub mod errors;
\ No newline at end of file
@@ -1 +1,11 @@
-pub type Url = String;
\ No newline at end of file
+use serde::Serialize;
+
+pub type Url = String;
+
+///A feed found while looking at a web page rather than a feed document
+#[derive(Debug, Clone, Serialize)]
+pub struct FeedCandidate {
+    pub url: Url,
+    pub title: Option<String>,
+    pub feed_type: Option<String>,
+}